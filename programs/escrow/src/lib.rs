@@ -1,297 +1,2877 @@
+//! Token escrow program with fixed price, Dutch, English and sealed-bid listings.
+//!
+//! # Calling from another program
+//!
+//! Build with the `cpi` feature to get an `escrow::cpi` module with one function per instruction, and an
+//! `escrow::accounts` module with the matching account structs:
+//!
+//! ```toml
+//! escrow = { path = "../escrow", features = ["cpi"] }
+//! ```
+//!
+//! A program that holds a mint's authority in one of its own PDAs can list newly minted tokens with
+//! `tender_from_mint_signed`, signing for the PDA with its seeds. The signature is forwarded to the token program's
+//! mint_to:
+//!
+//! ```ignore
+//! let seeds: &[&[&[u8]]] = &[&[b"mint_authority", &[mint_authority_bump]]];
+//! escrow::cpi::tender_from_mint_signed(
+//!     CpiContext::new_with_signer(
+//!         ctx.accounts.escrow_program.to_account_info(),
+//!         escrow::cpi::accounts::TenderFromMintSigned {
+//!             escrow_account: ctx.accounts.escrow_account.to_account_info(),
+//!             escrow_token_account: ctx.accounts.escrow_token_account.to_account_info(),
+//!             mint_authority: ctx.accounts.mint_authority.to_account_info(),
+//!             payer: ctx.accounts.payer.to_account_info(),
+//!             receiver: ctx.accounts.receiver.to_account_info(),
+//!             mint: ctx.accounts.mint.to_account_info(),
+//!             purchase_mint: ctx.accounts.purchase_mint.to_account_info(),
+//!             seller_proceeds_account: ctx.accounts.seller_proceeds_account.to_account_info(),
+//!             token_program: ctx.accounts.token_program.to_account_info(),
+//!             associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+//!             system_program: ctx.accounts.system_program.to_account_info(),
+//!             rent: ctx.accounts.rent.to_account_info(),
+//!         },
+//!         seeds,
+//!     ),
+//!     escrow_id, price_per_lot, lot_size, quantity, expires_at, escrow::FeeBearer::Seller, escrow::RoundingPolicy::Exact,
+//! )?;
+//! ```
+//!
+//! The escrow PDA is derived from the escrow program's id, with the seeds documented on [`TenderFromMintSigned`], not the caller's.
+//! `programs/mint-caller` is a complete example.
+
 use anchor_lang::prelude::*;
 use anchor_lang::AccountsClose;
 use anchor_lang::solana_program;
-use anchor_lang::solana_program::program_pack::Pack;
-use anchor_spl::{token, associated_token};
-use spl_associated_token_account::get_associated_token_address;
-use spl_token::state::Multisig;
+use anchor_spl::associated_token;
+use spl_token_2022::extension::transfer_fee::TransferFee;
 use std::convert::TryFrom;
 
+mod token_interface;
+
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
 const _ESCROW_SEED: &[u8] = "escrow".as_bytes();
+const _CONFIG_SEED: &[u8] = "config".as_bytes();
+const _BID_SEED: &[u8] = "bid".as_bytes();
+
+const _MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+/// The receiver used for open listings, which anyone may purchase to any account. This program reserves the zero key
+/// (the system program's address) as the sentinel, so it can't also be used as a real receiver.
+pub const OPEN_LISTING_RECEIVER: Pubkey = Pubkey::new_from_array([0; 32]);
+
+fn _check_tender_args(escrow_account: &EscrowAccount, price_per_lot: u64, lot_size: u64, add_qty: u64) -> ProgramResult {
+    // A lazy mint escrow's supply cap is fixed when it is tendered
+    if escrow_account.lazy_mint_authority.is_some() {
+        return Err(EscrowError::LazyMintEscrow.into());
+    }
+    if price_per_lot == 0 {
+        return Err(EscrowError::ZeroCost.into());
+    }
+    if lot_size == 0 || add_qty == 0 {
+        return Err(EscrowError::ZeroQuantity.into());
+    }
+    if add_qty % lot_size != 0 {
+        return Err(EscrowError::NonDivisiblePurchase.into());
+    }
+
+    // A fresh escrow has no lot size yet; later tenders must sell at the same price
+    if escrow_account.lot_size != 0 && (escrow_account.price_per_lot != price_per_lot || escrow_account.lot_size != lot_size) {
+        return Err(EscrowError::PriceRatioMismatch.into());
+    }
+    Ok(())
+}
+
+fn _get_lot_cost(qty: u64, lot_size: u64, price_per_lot: u64) -> Result<u64> {
+    let lots = qty.checked_div(lot_size).ok_or(EscrowError::Overflow)?;
+    let cost = (lots as u128).checked_mul(price_per_lot as u128).ok_or(EscrowError::Overflow)?;
+    return match u64::try_from(cost) {
+        Ok(c) => Ok(c),
+        Err(_) => Err(EscrowError::Overflow.into()),
+    }
+}
+
+/// The cost of qty at price_per_lot. Exact only prices whole lots; the other policies round a part lot's share
+fn _get_rounded_cost(qty: u64, lot_size: u64, price_per_lot: u64, rounding: RoundingPolicy) -> Result<u64> {
+    // cost = qty * price_per_lot / lot_size, which is only whole when the remainder is zero
+    let numerator = (qty as u128).checked_mul(price_per_lot as u128).ok_or(EscrowError::Overflow)?;
+    let cost = numerator.checked_div(lot_size as u128).ok_or(EscrowError::Overflow)?;
+    let remainder = numerator % lot_size as u128;
+    let cost = match rounding {
+        RoundingPolicy::Exact if qty % lot_size != 0 => return Err(EscrowError::NonDivisiblePurchase.into()),
+        RoundingPolicy::RoundUp if remainder != 0 => cost + 1,
+        RoundingPolicy::Exact | RoundingPolicy::RoundUp | RoundingPolicy::RoundDown { .. } => cost,
+    };
+    return match u64::try_from(cost) {
+        Ok(c) => Ok(c),
+        Err(_) => Err(EscrowError::Overflow.into()),
+    }
+}
+
+fn _get_purchase_cost(escrow_account: &EscrowAccount, qty: u64, total_qty: u64) -> Result<u64> {
+    if qty == 0 {
+        return Err(EscrowError::ZeroQuantity.into());
+    }
+    if qty > total_qty {
+        return Err(EscrowError::QuantityExceedsEscrow.into());
+    }
+
+    // The final purchase sweeps whatever cost remains, so rounded purchases still sum to the total cost. Rounding up
+    // can leave less than a later share would round to, so no purchase takes more than remains
+    if qty == total_qty {
+        return Ok(escrow_account.total_purchase_cost);
+    }
+    let cost = _get_rounded_cost(qty, escrow_account.lot_size, escrow_account.price_per_lot, escrow_account.rounding)?;
+    Ok(std::cmp::min(cost, escrow_account.total_purchase_cost))
+}
+
+/// A round down escrow's minimum applies to every purchase but the final one, which sweeps the remaining cost
+fn _check_min_cost(escrow_account: &EscrowAccount, qty: u64, total_qty: u64, cost: u64) -> ProgramResult {
+    match escrow_account.rounding {
+        RoundingPolicy::RoundDown { min_cost } if qty < total_qty && cost < min_cost => Err(EscrowError::PurchaseBelowMinimum.into()),
+        _ => Ok(()),
+    }
+}
 
-fn _check_tender_args(current_cost: u64, add_cost: u64, current_qty: u64, add_qty: u64) -> ProgramResult {
-    if add_cost == 0 || add_qty == 0 {
-        return Err(ProgramError::InvalidArgument);
+fn _check_dutch_auction_args(start_price: u64, end_price: u64, start_time: i64, end_time: i64) -> ProgramResult {
+    if end_price == 0 {
+        return Err(EscrowError::ZeroCost.into());
     }
+    if start_price < end_price {
+        return Err(EscrowError::InvalidAuctionPrices.into());
+    }
+    if end_time <= start_time {
+        return Err(EscrowError::InvalidAuctionTimes.into());
+    }
+    Ok(())
+}
+
+fn _get_dutch_auction_price(start_price: u64, end_price: u64, start_time: i64, end_time: i64, now: i64) -> Result<u64> {
+    if now <= start_time {
+        return Ok(start_price);
+    }
+    if now >= end_time {
+        return Ok(end_price);
+    }
+
+    // price = start_price - (start_price - end_price) * elapsed / duration. The decrease is rounded down, so the
+    // price is rounded up in the seller's favour
+    let elapsed = now.checked_sub(start_time).ok_or(EscrowError::Overflow)? as u128;
+    let duration = end_time.checked_sub(start_time).ok_or(EscrowError::Overflow)? as u128;
+    let decrease = ((start_price - end_price) as u128).checked_mul(elapsed).and_then(|r| r.checked_div(duration)).ok_or(EscrowError::Overflow)?;
+    let price = (start_price as u128).checked_sub(decrease).ok_or(EscrowError::Overflow)?;
+    return match u64::try_from(price) {
+        Ok(p) => Ok(p),
+        Err(_) => Err(EscrowError::Overflow.into()),
+    }
+}
 
-    // In real numbers we want (current_cost + add_cost) / current_cost = (current_qty + add_qty) / current_qty.
-    // This is equivalent algebraically to current_qty * (current_cost + add_cost) = current_cost * (current_qty + add_qty)
-    // ...current_qty * current_cost + current_qty * add_cost = current_cost * current_qty + current_cost * add_qty
-    // ...current_qty * add_cost = current_cost * add_qty
-    let lhs = (current_qty as u128).checked_mul(add_cost as u128).ok_or(ProgramError::InvalidArgument)?;
-    let rhs = (current_cost as u128).checked_mul(add_qty as u128).ok_or(ProgramError::InvalidArgument)?;
-    if lhs != rhs {
-        return Err(ProgramError::InvalidArgument);
+fn _check_english_auction_args(min_bid: u64, end_time: i64) -> ProgramResult {
+    if min_bid == 0 {
+        return Err(EscrowError::ZeroCost.into());
+    }
+    if end_time <= Clock::get()?.unix_timestamp {
+        return Err(EscrowError::InvalidAuctionTimes.into());
     }
     Ok(())
 }
 
-fn _get_purchase_cost(qty: u64, total_qty: u64, total_cost: u64) -> Result<u64, ProgramError> {
-    if  qty == 0 || qty > total_qty {
-        return Err(ProgramError::InvalidArgument);
+fn _check_sealed_bid_auction_args(min_bid: u64, commit_end_time: i64, reveal_end_time: i64) -> ProgramResult {
+    if min_bid == 0 {
+        return Err(EscrowError::ZeroCost.into());
+    }
+    if commit_end_time <= Clock::get()?.unix_timestamp || reveal_end_time <= commit_end_time {
+        return Err(EscrowError::InvalidAuctionTimes.into());
     }
+    Ok(())
+}
+
+fn _get_bid_commitment(escrow: &Pubkey, bidder: &Pubkey, bid: u64, salt: &[u8; 32]) -> [u8; 32] {
+    solana_program::hash::hashv(&[escrow.as_ref(), bidder.as_ref(), &bid.to_le_bytes(), salt]).to_bytes()
+}
 
-    // cost = (qty / total_qty) * total_cost
-    //       = (qty * total_cost) / total_qty
-    // to check, make sure total_qty * cost = qty * total_cost
-    let cost = (qty as u128).checked_mul(total_cost as u128).and_then(|r| r.checked_div(total_qty as u128)).ok_or(ProgramError::InvalidArgument)?;
-    let lhs = (total_qty as u128).checked_mul(cost as u128).ok_or(ProgramError::InvalidArgument)?;
-    let rhs = (qty as u128).checked_mul(total_cost as u128).ok_or(ProgramError::InvalidArgument)?;
-    if lhs != rhs {
-        return Err(ProgramError::InvalidArgument);
+fn _get_dutch_auction_cost(qty: u64, total_qty: u64, unit_price: u64) -> Result<u64> {
+    if qty == 0 {
+        return Err(EscrowError::ZeroQuantity.into());
     }
+    if qty > total_qty {
+        return Err(EscrowError::QuantityExceedsEscrow.into());
+    }
+
+    let cost = (qty as u128).checked_mul(unit_price as u128).ok_or(EscrowError::Overflow)?;
     return match u64::try_from(cost) {
         Ok(c) => Ok(c),
-        Err(_) => Err(ProgramError::InvalidArgument),
+        Err(_) => Err(EscrowError::Overflow.into()),
+    }
+}
+
+fn _get_protocol_fee(cost: u64, fee_basis_points: u16) -> Result<u64> {
+    // fee = cost * fee_basis_points / 10000, rounded down in the seller's favour
+    let fee = (cost as u128).checked_mul(fee_basis_points as u128).and_then(|r| r.checked_div(_MAX_FEE_BASIS_POINTS as u128)).ok_or(EscrowError::Overflow)?;
+    return match u64::try_from(fee) {
+        Ok(f) => Ok(f),
+        Err(_) => Err(EscrowError::Overflow.into()),
+    }
+}
+
+fn _gross_up_for_transfer_fee(net_amount: u64, transfer_fee: Option<TransferFee>) -> Result<u64> {
+    let transfer_fee = match transfer_fee {
+        Some(transfer_fee) => transfer_fee,
+        None => return Ok(net_amount),
+    };
+    let fee_basis_points = u16::from(transfer_fee.transfer_fee_basis_points) as u128;
+    let maximum_fee = u64::from(transfer_fee.maximum_fee);
+    if fee_basis_points == 0 || net_amount == 0 {
+        return Ok(net_amount);
+    }
+
+    // The token program charges fee = ceil(gross * fee_basis_points / 10000), capped at maximum_fee, so we want the
+    // smallest gross with gross - fee >= net. Uncapped, that is gross = ceil(net * 10000 / (10000 - fee_basis_points))
+    let capped = net_amount.checked_add(maximum_fee).ok_or(EscrowError::Overflow)?;
+    let max_basis_points = _MAX_FEE_BASIS_POINTS as u128;
+    if fee_basis_points >= max_basis_points {
+        return Ok(capped);
+    }
+    let divisor = max_basis_points - fee_basis_points;
+    let uncapped = (net_amount as u128).checked_mul(max_basis_points).and_then(|r| r.checked_add(divisor - 1)).ok_or(EscrowError::Overflow)? / divisor;
+    return match u64::try_from(uncapped) {
+        Ok(gross) => Ok(std::cmp::min(gross, capped)),
+        Err(_) => Ok(capped),
+    }
+}
+
+fn _check_fee_basis_points(fee_basis_points: u16) -> ProgramResult {
+    if fee_basis_points > _MAX_FEE_BASIS_POINTS {
+        return Err(EscrowError::InvalidFee.into());
+    }
+    Ok(())
+}
+
+/// The global configuration, or one with no fee if it hasn't been created, so a deployment without a config is fee-free
+fn _load_config(config: &AccountInfo) -> Result<ConfigAccount> {
+    if config.data_is_empty() {
+        return Ok(ConfigAccount::default());
+    }
+    Ok(Account::<ConfigAccount>::try_from(config)?.into_inner())
+}
+
+fn _check_treasury_account(treasury_account: &AccountInfo, purchase_mint: &Pubkey, config: &ConfigAccount) -> ProgramResult {
+    if token_interface::token_account_mint(treasury_account) != Some(*purchase_mint) {
+        return Err(EscrowError::WrongMint.into());
+    }
+    if token_interface::token_account_owner(treasury_account) != Some(config.treasury) {
+        return Err(EscrowError::WrongOwner.into());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn _init_escrow_terms(
+    escrow_account: &mut EscrowAccount,
+    seller: Pubkey,
+    receiver: Pubkey,
+    mint: Pubkey,
+    purchase_mint: Pubkey,
+    seller_proceeds_account: Pubkey,
+    rent_payer: Pubkey,
+    expires_at: Option<i64>,
+    fee_bearer: FeeBearer,
+    pricing_mode: PricingMode,
+    rounding: RoundingPolicy,
+    minted: bool,
+    escrow_id: u64,
+    bump_seed: u8,
+) -> ProgramResult {
+    let clock = Clock::get()?;
+
+    // A freshly created escrow has no rent payer recorded; later tenders must keep the original terms
+    if escrow_account.rent_payer != Pubkey::default() {
+        if escrow_account.expires_at != expires_at
+            || escrow_account.fee_bearer != fee_bearer
+            || escrow_account.pricing_mode != pricing_mode
+            || escrow_account.rounding != rounding
+            || escrow_account.minted != minted {
+            return Err(EscrowError::TermsMismatch.into());
+        }
+        return _check_not_expired(escrow_account, &clock);
+    }
+
+    if let Some(expires_at) = expires_at {
+        if expires_at <= clock.unix_timestamp {
+            return Err(EscrowError::InvalidExpiry.into());
+        }
+    }
+    if let RoundingPolicy::RoundDown { min_cost: 0 } = rounding {
+        return Err(EscrowError::ZeroCost.into());
+    }
+
+    escrow_account.seller = seller;
+    escrow_account.receiver = receiver;
+    escrow_account.mint = mint;
+    escrow_account.purchase_mint = purchase_mint;
+    escrow_account.seller_proceeds_account = seller_proceeds_account;
+    escrow_account.rent_payer = rent_payer;
+    escrow_account.created_at_slot = clock.slot;
+    escrow_account.created_at_timestamp = clock.unix_timestamp;
+    escrow_account.expires_at = expires_at;
+    escrow_account.fee_bearer = fee_bearer;
+    escrow_account.pricing_mode = pricing_mode;
+    escrow_account.rounding = rounding;
+    escrow_account.minted = minted;
+    escrow_account.escrow_id = escrow_id;
+
+    // Store the canonical bump Anchor derived rather than trusting one from the client, so later instructions can sign
+    // with it
+    escrow_account.bump_seed = bump_seed;
+    Ok(())
+}
+
+fn _check_not_expired(escrow_account: &EscrowAccount, clock: &Clock) -> ProgramResult {
+    match escrow_account.expires_at {
+        Some(expires_at) if clock.unix_timestamp >= expires_at => Err(EscrowError::EscrowExpired.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The cost of quantity at the escrow's current price
+fn _get_current_cost(escrow_account: &EscrowAccount, escrow_quantity: u64, quantity: u64, clock: &Clock) -> Result<u64> {
+    match escrow_account.pricing_mode {
+        PricingMode::Fixed => _get_purchase_cost(escrow_account, quantity, escrow_quantity),
+        PricingMode::DutchAuction { start_price, end_price, start_time, end_time } => {
+            let unit_price = _get_dutch_auction_price(start_price, end_price, start_time, end_time, clock.unix_timestamp)?;
+            _get_dutch_auction_cost(quantity, escrow_quantity, unit_price)
+        },
+        PricingMode::EnglishAuction { .. } | PricingMode::SealedBidAuction { .. } => Err(EscrowError::NotPurchasable.into()),
+    }
+}
+
+fn _get_purchase_payment(escrow_account: &EscrowAccount, escrow_quantity: u64, quantity: u64, fee_basis_points: u16) -> Result<(u64, u64)> {
+    let clock = Clock::get()?;
+    _check_not_expired(escrow_account, &clock)?;
+
+    let purchase_cost = _get_current_cost(escrow_account, escrow_quantity, quantity, &clock)?;
+    _check_min_cost(escrow_account, quantity, escrow_quantity, purchase_cost)?;
+    let protocol_fee = _get_protocol_fee(purchase_cost, fee_basis_points)?;
+    Ok((purchase_cost, protocol_fee))
+}
+
+/// The most of the escrow that max_spend buys, counting everything the buyer sends: the protocol fee and, if they bear
+/// it, the purchase mint's transfer fee
+fn _get_budget_quantity(escrow_account: &EscrowAccount, escrow_quantity: u64, max_spend: u64, fee_basis_points: u16, transfer_fee: Option<TransferFee>) -> Result<u64> {
+    let step = match (escrow_account.pricing_mode, escrow_account.rounding) {
+        (PricingMode::Fixed, RoundingPolicy::Exact) => escrow_account.lot_size,
+        (PricingMode::Fixed, _) | (PricingMode::DutchAuction { .. }, _) => 1,
+        (PricingMode::EnglishAuction { .. }, _) | (PricingMode::SealedBidAuction { .. }, _) => return Err(EscrowError::NotPurchasable.into()),
+    };
+
+    // The fees are rounded, so there's no closed form; but what the buyer sends only grows with the quantity, so
+    // binary search the number of steps
+    let clock = Clock::get()?;
+    let (mut low, mut high) = (0, escrow_quantity / step);
+    let mut low_cost = 0;
+    while low < high {
+        let mid = high - (high - low) / 2;
+        let purchase_cost = _get_current_cost(escrow_account, escrow_quantity, mid * step, &clock)?;
+        let protocol_fee = _get_protocol_fee(purchase_cost, fee_basis_points)?;
+        if _get_buyer_debit(purchase_cost, protocol_fee, transfer_fee)? <= max_spend {
+            low = mid;
+            low_cost = purchase_cost;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    // The cost only grows with the quantity too, so if a round down escrow's minimum rules out the most the budget
+    // affords, it rules out everything less
+    if low == 0 || _check_min_cost(escrow_account, low * step, escrow_quantity, low_cost).is_err() {
+        return Err(EscrowError::InsufficientBudget.into());
+    }
+    Ok(low * step)
+}
+
+/// The quantity left to sell. A lazy mint escrow holds no tokens, so this is what remains under its supply cap
+fn _get_escrow_quantity(escrow_account: &EscrowAccount, escrow_token_account: &AccountInfo) -> Result<u64> {
+    match escrow_account.lazy_mint_authority {
+        Some(_) => Ok(escrow_account.original_quantity.checked_sub(escrow_account.quantity_sold).ok_or(EscrowError::Overflow)?),
+        None => Ok(token_interface::unpack_token_account(escrow_token_account)?.amount),
+    }
+}
+
+/// What a purchase actually debits from the buyer: the seller's proceeds and the protocol fee, each grossed up when the
+/// buyer bears the purchase mint's transfer fee
+fn _get_buyer_debit(purchase_cost: u64, protocol_fee: u64, transfer_fee: Option<TransferFee>) -> Result<u64> {
+    let seller_proceeds = purchase_cost.checked_sub(protocol_fee).ok_or(EscrowError::Overflow)?;
+    _gross_up_for_transfer_fee(seller_proceeds, transfer_fee)?
+        .checked_add(_gross_up_for_transfer_fee(protocol_fee, transfer_fee)?)
+        .ok_or_else(|| EscrowError::Overflow.into())
+}
+
+/// The purchase mint's transfer fee if the buyer bears it, since then they send enough extra for the full amounts to
+/// arrive
+fn _get_buyer_transfer_fee(escrow_account: &EscrowAccount, purchase_mint: &AccountInfo) -> Result<Option<TransferFee>> {
+    match escrow_account.fee_bearer {
+        FeeBearer::Buyer => Ok(token_interface::get_transfer_fee(purchase_mint, Clock::get()?.epoch)?),
+        FeeBearer::Seller => Ok(None),
+    }
+}
+
+fn _check_max_cost(buyer_debit: u64, max_cost: u64) -> ProgramResult {
+    if buyer_debit > max_cost {
+        return Err(EscrowError::MaxCostExceeded.into());
+    }
+    Ok(())
+}
+
+fn _record_purchase(escrow_account: &mut EscrowAccount, quantity: u64, cost: u64) -> ProgramResult {
+    // Only a fixed price escrow has a total cost to draw down; an auction's cost comes from its schedule
+    if escrow_account.pricing_mode == PricingMode::Fixed {
+        escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_sub(cost).ok_or(EscrowError::Overflow)?;
+    }
+    escrow_account.quantity_sold = escrow_account.quantity_sold.checked_add(quantity).ok_or(EscrowError::Overflow)?;
+    Ok(())
+}
+
+/// Call f with the seeds the escrow PDA signs with
+fn _with_escrow_signer_seeds<R>(escrow_account: &EscrowAccount, f: impl FnOnce(&[&[&[u8]]]) -> R) -> R {
+    f(&[&[
+        _ESCROW_SEED,
+        escrow_account.seller_proceeds_account.as_ref(),
+        escrow_account.receiver.as_ref(),
+        escrow_account.mint.as_ref(),
+        escrow_account.purchase_mint.as_ref(),
+        escrow_account.rent_payer.as_ref(),
+        &escrow_account.escrow_id.to_le_bytes(),
+        &[escrow_account.bump_seed],
+    ]])
+}
+
+fn _transfer_from_escrow<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    escrow_token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    quantity: u64,
+) -> ProgramResult {
+    _with_escrow_signer_seeds(escrow_account, |signer_seeds| {
+        token_interface::transfer_checked(
+            token_program,
+            escrow_token_account,
+            mint,
+            to,
+            escrow_account.to_account_info(),
+            quantity,
+            signer_seeds,
+        )
+    })
+}
+
+fn _burn_from_escrow<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    escrow_token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    quantity: u64,
+) -> ProgramResult {
+    _with_escrow_signer_seeds(escrow_account, |signer_seeds| {
+        token_interface::burn(
+            token_program,
+            escrow_token_account,
+            mint,
+            escrow_account.to_account_info(),
+            quantity,
+            signer_seeds,
+        )
+    })
+}
+
+/// Send purchased tokens to the buyer, minting them if the escrow is a lazy mint escrow
+fn _deliver_from_escrow<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    escrow_token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    quantity: u64,
+) -> ProgramResult {
+    if escrow_account.lazy_mint_authority.is_none() {
+        return _transfer_from_escrow(escrow_account, escrow_token_account, mint, to, token_program, quantity);
+    }
+    _with_escrow_signer_seeds(escrow_account, |signer_seeds| {
+        token_interface::mint_to(
+            token_program,
+            mint,
+            to,
+            escrow_account.to_account_info(),
+            &[],
+            quantity,
+            signer_seeds,
+        )
+    })
+}
+
+/// Hand a lazy mint escrow's mint authority back to whoever held it before. If the escrow was the mint's original
+/// authority, nobody can mint more once it closes
+fn _release_mint_authority<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    mint: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> ProgramResult {
+    let original_authority = match escrow_account.lazy_mint_authority {
+        Some(authority) => authority,
+        None => return Ok(()),
+    };
+    let new_authority = if original_authority == escrow_account.key() { None } else { Some(original_authority) };
+    _with_escrow_signer_seeds(escrow_account, |signer_seeds| {
+        token_interface::set_mint_authority(
+            token_program,
+            mint,
+            new_authority,
+            escrow_account.to_account_info(),
+            &[],
+            signer_seeds,
+        )
+    })
+}
+
+fn _close_escrow_token_account<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> ProgramResult {
+    _with_escrow_signer_seeds(escrow_account, |signer_seeds| {
+        token_interface::close_account(
+            token_program,
+            token_account,
+            mint,
+            destination,
+            escrow_account.to_account_info(),
+            signer_seeds,
+        )
+    })
+}
+
+fn _close_escrow_if_empty<'info>(
+    escrow_account: &Account<'info, EscrowAccount>,
+    escrow_token_account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    rent_payer: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> ProgramResult {
+    if _get_escrow_quantity(escrow_account, &escrow_token_account)? != 0 {
+        return Ok(());
+    }
+
+    // A lazy mint escrow mints straight to the buyer, so it has no token account to close
+    if escrow_account.lazy_mint_authority.is_none() {
+        _close_escrow_token_account(escrow_account, escrow_token_account, mint.clone(), rent_payer.clone(), token_program.clone())?;
+    }
+    _release_mint_authority(escrow_account, mint, token_program)?;
+    escrow_account.close(rent_payer)?;
+
+    emit!(EscrowClosed {
+        escrow: escrow_account.key(),
+        seller_proceeds_account: escrow_account.seller_proceeds_account,
+        receiver: escrow_account.receiver,
+        mint: escrow_account.mint,
+        purchase_mint: escrow_account.purchase_mint,
+        rent_payer: escrow_account.rent_payer,
+        escrow_id: escrow_account.escrow_id,
+    });
+    Ok(())
+}
+
+fn _transfer_from_bid_deposit<'info>(
+    bid_commitment: &Account<'info, BidCommitment>,
+    deposit_vault: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> ProgramResult {
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        _BID_SEED,
+        &bid_commitment.escrow.to_bytes(),
+        &bid_commitment.bidder.to_bytes(),
+        &[bid_commitment.bump_seed]
+        ]];
+
+    token_interface::transfer_checked(
+        token_program,
+        deposit_vault,
+        mint,
+        to,
+        bid_commitment.to_account_info(),
+        amount,
+        signer_seeds,
+    )
+}
+
+fn _close_bid_deposit<'info>(
+    bid_commitment: &Account<'info, BidCommitment>,
+    deposit_vault: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> ProgramResult {
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        _BID_SEED,
+        &bid_commitment.escrow.to_bytes(),
+        &bid_commitment.bidder.to_bytes(),
+        &[bid_commitment.bump_seed]
+        ]];
+
+    token_interface::close_account(
+        token_program,
+        deposit_vault,
+        mint,
+        destination,
+        bid_commitment.to_account_info(),
+        signer_seeds,
+    )
+}
+
+fn _get_multisig_signers<'info>(authority: &AccountInfo<'info>, remaining_accounts: &[AccountInfo<'info>]) -> Result<Vec<AccountInfo<'info>>> {
+    let multisig = match token_interface::unpack_multisig(authority)? {
+        Some(multisig) => multisig,
+        None => return Ok(Vec::new()),
+    };
+
+    // Count each of the multisig's listed signers once, however many times it is passed
+    let listed_signers = &multisig.signers[..multisig.n as usize];
+    let mut signers: Vec<AccountInfo<'info>> = Vec::new();
+    for account_info in remaining_accounts {
+        if account_info.is_signer
+            && listed_signers.contains(account_info.key)
+            && !signers.iter().any(|signer| signer.key == account_info.key) {
+            signers.push(account_info.clone());
+        }
+    }
+    if signers.len() < multisig.m as usize {
+        return Err(EscrowError::MultisigThresholdNotMet.into());
+    }
+    Ok(signers)
+}
+
+fn _wrap_sol<'info>(
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    lamports: u64,
+) -> ProgramResult {
+    // Move the lamports into the wrapped SOL account, then have the token program credit them to its balance
+    solana_program::program::invoke(
+        &solana_program::system_instruction::transfer(from.key, to.key, lamports),
+        &[from, to.clone(), system_program],
+    )?;
+    token_interface::sync_native(token_program, to)
+}
+
+/// Move a seller's tokens into the escrow under the given terms, creating the escrow's token account if needed, and
+/// return how many arrived. A mint with a transfer fee withholds part of the transfer, so that is what gets sold
+#[allow(clippy::too_many_arguments)]
+fn _tender_into_escrow<'info>(
+    escrow_account: &mut Account<'info, EscrowAccount>,
+    seller: AccountInfo<'info>,
+    receiver: Pubkey,
+    mint: AccountInfo<'info>,
+    purchase_mint: Pubkey,
+    seller_proceeds_account: Pubkey,
+    sell_from_account: AccountInfo<'info>,
+    escrow_token_account: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+    escrow_id: u64,
+    asset_quantity_for_sale: u64,
+    expires_at: Option<i64>,
+    fee_bearer: FeeBearer,
+    pricing_mode: PricingMode,
+    rounding: RoundingPolicy,
+    bump_seed: u8,
+) -> Result<u64> {
+    token_interface::create_associated_token_account_if_needed(
+        seller.clone(),
+        escrow_token_account.clone(),
+        escrow_account.to_account_info(),
+        mint.clone(),
+        system_program,
+        token_program.clone(),
+        associated_token_program,
+        rent,
+    )?;
+    let escrow_quantity = token_interface::unpack_token_account(&escrow_token_account)?.amount;
+
+    _init_escrow_terms(
+        escrow_account,
+        seller.key(),
+        receiver,
+        mint.key(),
+        purchase_mint,
+        seller_proceeds_account,
+        seller.key(),
+        expires_at,
+        fee_bearer,
+        pricing_mode,
+        rounding,
+        false,
+        escrow_id,
+        bump_seed,
+    )?;
+
+    token_interface::transfer_checked(
+        token_program,
+        sell_from_account,
+        mint,
+        escrow_token_account.clone(),
+        seller,
+        asset_quantity_for_sale,
+        &[],
+    )?;
+
+    let quantity_received = token_interface::unpack_token_account(&escrow_token_account)?.amount.checked_sub(escrow_quantity).ok_or(EscrowError::Overflow)?;
+    if quantity_received == 0 {
+        return Err(EscrowError::ZeroQuantity.into());
     }
+    escrow_account.original_quantity = escrow_account.original_quantity.checked_add(quantity_received).ok_or(EscrowError::Overflow)?;
+    Ok(quantity_received)
+}
+
+/// Mint new tokens into a fixed price escrow under the given terms, creating the escrow's token account if needed, and
+/// return what the minted quantity costs. The seller is whoever owns the seller_proceeds_account
+#[allow(clippy::too_many_arguments)]
+fn _mint_into_escrow<'info>(
+    escrow_account: &mut Account<'info, EscrowAccount>,
+    escrow_token_account: AccountInfo<'info>,
+    mint_authority: AccountInfo<'info>,
+    multisig_signers: &[AccountInfo<'info>],
+    payer: AccountInfo<'info>,
+    receiver: Pubkey,
+    mint: AccountInfo<'info>,
+    purchase_mint: Pubkey,
+    seller_proceeds_account: &AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+    escrow_id: u64,
+    price_per_lot: u64,
+    lot_size: u64,
+    asset_quantity_for_sale: u64,
+    expires_at: Option<i64>,
+    fee_bearer: FeeBearer,
+    rounding: RoundingPolicy,
+    bump_seed: u8,
+) -> Result<u64> {
+    token_interface::create_associated_token_account_if_needed(
+        payer.clone(),
+        escrow_token_account.clone(),
+        escrow_account.to_account_info(),
+        mint.clone(),
+        system_program,
+        token_program.clone(),
+        associated_token_program,
+        rent,
+    )?;
+    let seller = token_interface::unpack_token_account(seller_proceeds_account)?.owner;
+
+    _check_tender_args(escrow_account, price_per_lot, lot_size, asset_quantity_for_sale)?;
+    let cost = _get_lot_cost(asset_quantity_for_sale, lot_size, price_per_lot)?;
+    _init_escrow_terms(
+        escrow_account,
+        seller,
+        receiver,
+        mint.key(),
+        purchase_mint,
+        seller_proceeds_account.key(),
+        payer.key(),
+        expires_at,
+        fee_bearer,
+        PricingMode::Fixed,
+        rounding,
+        true,
+        escrow_id,
+        bump_seed,
+    )?;
+
+    token_interface::mint_to(
+        token_program,
+        mint,
+        escrow_token_account,
+        mint_authority,
+        multisig_signers,
+        asset_quantity_for_sale,
+        &[],
+    )?;
+
+    escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_add(cost).ok_or(EscrowError::Overflow)?;
+    escrow_account.original_quantity = escrow_account.original_quantity.checked_add(asset_quantity_for_sale).ok_or(EscrowError::Overflow)?;
+    escrow_account.price_per_lot = price_per_lot;
+    escrow_account.lot_size = lot_size;
+    Ok(cost)
 }
 
 #[program]
 pub mod escrow {
     use super::*;
 
-    pub fn tender(ctx: Context<Tender>, bump_seed: u8, total_purchase_cost: u64, asset_quantity_for_sale: u64) -> ProgramResult {
+    #[allow(clippy::too_many_arguments)]
+    pub fn tender(
+        ctx: Context<Tender>,
+        escrow_id: u64, price_per_lot: u64, lot_size: u64, asset_quantity_for_sale: u64, expires_at: Option<i64>, fee_bearer: FeeBearer,
+        rounding: RoundingPolicy
+    ) -> ProgramResult {
+        let quantity_received = _tender_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            ctx.accounts.seller_proceeds_account.key(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            asset_quantity_for_sale,
+            expires_at,
+            fee_bearer,
+            PricingMode::Fixed,
+            rounding,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
         let escrow_account = &mut ctx.accounts.escrow_account;
-        let escrow_token_account = &mut ctx.accounts.escrow_token_account;
 
-        _check_tender_args(escrow_account.total_purchase_cost, total_purchase_cost, escrow_token_account.amount, asset_quantity_for_sale)?;
+        // A mint with a transfer fee withholds part of the transfer, so what actually arrived must be whole lots
+        _check_tender_args(escrow_account, price_per_lot, lot_size, quantity_received)?;
+        let cost = _get_lot_cost(quantity_received, lot_size, price_per_lot)?;
 
-        let transfer_ctx = CpiContext::new(ctx.accounts.token_program.clone(), token::Transfer {
-            authority: ctx.accounts.seller.to_account_info(),
-            from: ctx.accounts.sell_from_account.to_account_info(),
-            to: escrow_token_account.to_account_info(),
-        });
-        token::transfer(transfer_ctx, asset_quantity_for_sale)?;
+        escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_add(cost).ok_or(EscrowError::Overflow)?;
+        escrow_account.price_per_lot = price_per_lot;
+        escrow_account.lot_size = lot_size;
 
-        escrow_account.total_purchase_cost += total_purchase_cost;
-        escrow_account.bump_seed = bump_seed;
+        emit!(EscrowTendered {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: escrow_account.escrow_id,
+            quantity: quantity_received,
+            price_per_lot,
+            lot_size,
+            cost,
+            total_purchase_cost: escrow_account.total_purchase_cost,
+        });
         
         Ok(())
     }
 
+    /// Tender into a Dutch auction, whose unit price falls linearly from start_price at start_time to end_price at end_time
+    #[allow(clippy::too_many_arguments)]
+    pub fn tender_dutch(
+        ctx: Context<Tender>,
+        escrow_id: u64, asset_quantity_for_sale: u64, start_price: u64, end_price: u64, start_time: i64, end_time: i64, expires_at: Option<i64>, fee_bearer: FeeBearer
+    ) -> ProgramResult {
+        _check_dutch_auction_args(start_price, end_price, start_time, end_time)?;
+        let quantity_received = _tender_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            ctx.accounts.seller_proceeds_account.key(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            asset_quantity_for_sale,
+            expires_at,
+            fee_bearer,
+            PricingMode::DutchAuction { start_price, end_price, start_time, end_time },
+            RoundingPolicy::Exact,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        emit!(EscrowDutchAuctionTendered {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: quantity_received,
+            start_price,
+            end_price,
+            start_time,
+            end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Tender into an English auction, which is sold whole to the highest bid once end_time passes
+    pub fn tender_english(ctx: Context<TenderEnglish>, escrow_id: u64, asset_quantity_for_sale: u64, min_bid: u64, end_time: i64) -> ProgramResult {
+        _check_english_auction_args(min_bid, end_time)?;
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.bid_vault.clone(),
+            ctx.accounts.escrow_account.to_account_info(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.purchase_token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+
+        // Bids are locked in the vault until the auction settles, so an auction can't also expire
+        let quantity_received = _tender_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            ctx.accounts.seller_proceeds_account.key(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            asset_quantity_for_sale,
+            None,
+            FeeBearer::Seller,
+            PricingMode::EnglishAuction { min_bid, end_time },
+            RoundingPolicy::Exact,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        emit!(EscrowEnglishAuctionTendered {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: quantity_received,
+            min_bid,
+            end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Tender into a sealed-bid auction. Bids are committed as hashes until commit_end_time, revealed until
+    /// reveal_end_time, and the whole escrow is then sold to the highest revealed bid
+    pub fn tender_sealed(ctx: Context<Tender>, escrow_id: u64, asset_quantity_for_sale: u64, min_bid: u64, commit_end_time: i64, reveal_end_time: i64) -> ProgramResult {
+        _check_sealed_bid_auction_args(min_bid, commit_end_time, reveal_end_time)?;
+        // Deposits are locked until the auction settles, so an auction can't also expire
+        let quantity_received = _tender_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            ctx.accounts.seller_proceeds_account.key(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            asset_quantity_for_sale,
+            None,
+            FeeBearer::Seller,
+            PricingMode::SealedBidAuction { min_bid, commit_end_time, reveal_end_time },
+            RoundingPolicy::Exact,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        emit!(EscrowSealedBidAuctionTendered {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: quantity_received,
+            min_bid,
+            commit_end_time,
+            reveal_end_time,
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn tender_from_mint<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, TenderFromMint<'info>>,
-        bump_seed: u8, total_purchase_cost: u64, asset_quantity_for_sale: u64
+        escrow_id: u64, price_per_lot: u64, lot_size: u64, asset_quantity_for_sale: u64, expires_at: Option<i64>, fee_bearer: FeeBearer,
+        rounding: RoundingPolicy
+    ) -> ProgramResult {
+        // A multi-sig mint authority can't sign itself, so its signers are passed as remaining accounts
+        let signers = _get_multisig_signers(&ctx.accounts.mint_authority, ctx.remaining_accounts)?;
+        let cost = _mint_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint_authority.clone(),
+            signers.as_slice(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            &ctx.accounts.seller_proceeds_account,
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            price_per_lot,
+            lot_size,
+            asset_quantity_for_sale,
+            expires_at,
+            fee_bearer,
+            rounding,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        emit!(EscrowMinted {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: asset_quantity_for_sale,
+            price_per_lot,
+            lot_size,
+            cost,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+        
+        Ok(())
+    }
+
+    /// Tender from a mint whose authority must sign directly, such as a PDA of a program calling this through CPI with
+    /// invoke_signed. Unlike tender_from_mint, a multisig authority isn't accepted
+    #[allow(clippy::too_many_arguments)]
+    pub fn tender_from_mint_signed(
+        ctx: Context<TenderFromMintSigned>,
+        escrow_id: u64, price_per_lot: u64, lot_size: u64, asset_quantity_for_sale: u64, expires_at: Option<i64>, fee_bearer: FeeBearer,
+        rounding: RoundingPolicy
+    ) -> ProgramResult {
+        let cost = _mint_into_escrow(
+            &mut ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint_authority.to_account_info(),
+            &[],
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.purchase_mint.key(),
+            &ctx.accounts.seller_proceeds_account,
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            escrow_id,
+            price_per_lot,
+            lot_size,
+            asset_quantity_for_sale,
+            expires_at,
+            fee_bearer,
+            rounding,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        emit!(EscrowMinted {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: asset_quantity_for_sale,
+            price_per_lot,
+            lot_size,
+            cost,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+
+        Ok(())
+    }
+
+    /// Tender a fixed price escrow that mints each purchase rather than holding tokens, selling at most supply_cap.
+    /// The escrow takes over the mint authority until it closes, so unsold tokens never exist. The mint authority
+    /// may already be the escrow, in which case it is revoked once the escrow closes
+    #[allow(clippy::too_many_arguments)]
+    pub fn tender_lazy_mint<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, TenderLazyMint<'info>>,
+        escrow_id: u64, price_per_lot: u64, lot_size: u64, supply_cap: u64, expires_at: Option<i64>, fee_bearer: FeeBearer,
+        rounding: RoundingPolicy
     ) -> ProgramResult {
         let escrow_account = &mut ctx.accounts.escrow_account;
-        let escrow_token_account = &mut ctx.accounts.escrow_token_account;
-
-        _check_tender_args(escrow_account.total_purchase_cost, total_purchase_cost, escrow_token_account.amount, asset_quantity_for_sale)?;
-
-        // TODO: switch to anchor CPI once they support multi-sig
-        if ctx.accounts.mint_authority.to_account_info().data_len() == Multisig::get_packed_len() {
-            let mut signers: std::vec::Vec<AccountInfo> = std::vec::Vec::new();
-            let mut signer_keys: std::vec::Vec<& Pubkey> = std::vec::Vec::new();
-            for account_info in ctx.remaining_accounts {
-                if account_info.is_signer {
-                    signers.push(account_info.clone());
-                    signer_keys.push(account_info.key);
-                }
-            }
-            let ix = spl_token::instruction::mint_to(
-                ctx.accounts.token_program.key,
-                ctx.accounts.mint.to_account_info().key,
-                ctx.accounts.escrow_token_account.to_account_info().key,
-                ctx.accounts.mint_authority.key,
-                signer_keys.as_slice(),
-                asset_quantity_for_sale,
-            )?;
-            let mut account_infos = vec!(
-                ctx.accounts.escrow_token_account.to_account_info(),
-                ctx.accounts.mint.to_account_info(),
-                ctx.accounts.mint_authority.clone(),
+        let seller = token_interface::unpack_token_account(&ctx.accounts.seller_proceeds_account)?.owner;
+
+        _check_tender_args(escrow_account, price_per_lot, lot_size, supply_cap)?;
+        let cost = _get_lot_cost(supply_cap, lot_size, price_per_lot)?;
+        _init_escrow_terms(
+            escrow_account,
+            seller,
+            ctx.accounts.receiver.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.purchase_mint.key(),
+            ctx.accounts.seller_proceeds_account.key(),
+            ctx.accounts.payer.key(),
+            expires_at,
+            fee_bearer,
+            PricingMode::Fixed,
+            rounding,
+            false,
+            escrow_id,
+            *ctx.bumps.get("escrow_account").unwrap(),
+        )?;
+
+        let original_authority = token_interface::mint_authority(&ctx.accounts.mint).ok_or(EscrowError::NoMintAuthority)?;
+        if original_authority != escrow_account.key() {
+            // A multi-sig mint authority can't sign itself, so its signers are passed as remaining accounts
+            let signers = _get_multisig_signers(&ctx.accounts.mint_authority, ctx.remaining_accounts)?;
+            token_interface::set_mint_authority(
                 ctx.accounts.token_program.clone(),
-            );
-            account_infos.append(&mut signers);
-            solana_program::program::invoke_signed(
-                &ix,
-                account_infos.as_slice(),
+                ctx.accounts.mint.clone(),
+                Some(escrow_account.key()),
+                ctx.accounts.mint_authority.clone(),
+                signers.as_slice(),
                 &[],
             )?;
-        } else {
-            let mint_ctx = CpiContext::new(ctx.accounts.token_program.clone(), token::MintTo {
-                authority: ctx.accounts.mint_authority.to_account_info(),
-                mint: ctx.accounts.mint.to_account_info(),
-                to: escrow_token_account.to_account_info(),
-            });
-            token::mint_to(mint_ctx, asset_quantity_for_sale)?;
         }
 
-        escrow_account.total_purchase_cost += total_purchase_cost;
-        escrow_account.bump_seed = bump_seed;
-        
+        escrow_account.total_purchase_cost = cost;
+        escrow_account.original_quantity = supply_cap;
+        escrow_account.price_per_lot = price_per_lot;
+        escrow_account.lot_size = lot_size;
+        escrow_account.lazy_mint_authority = Some(original_authority);
+
+        emit!(EscrowLazyMintTendered {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.payer.key(),
+            escrow_id: escrow_account.escrow_id,
+            supply_cap,
+            price_per_lot,
+            lot_size,
+            total_purchase_cost: cost,
+        });
+
         Ok(())
     }
 
     pub fn purchase(ctx: Context<Purchase>) -> ProgramResult {
-        let quantity_remaining = ctx.accounts.escrow_token_account.amount;
-        purchase_partial(ctx, quantity_remaining)?;
+        purchase_with_max_cost(ctx, u64::MAX)
+    }
+
+    /// Purchase the whole escrow, failing if it costs more than max_cost
+    pub fn purchase_with_max_cost(ctx: Context<Purchase>, max_cost: u64) -> ProgramResult {
+        let quantity_remaining = _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?;
+        purchase_partial(ctx, quantity_remaining, max_cost)?;
 
         Ok(())
     }
 
-    pub fn purchase_partial(ctx: Context<Purchase>, quantity_to_transfer: u64) -> ProgramResult {
-        let escrow_account = &mut ctx.accounts.escrow_account;
+    /// Purchase part of the escrow, failing if it costs more than max_cost. The price can move between the buyer
+    /// quoting it and the purchase landing, e.g. when the seller tenders more
+    pub fn purchase_partial(ctx: Context<Purchase>, quantity_to_transfer: u64, max_cost: u64) -> ProgramResult {
+        let config = _load_config(&ctx.accounts.config)?;
+        let (purchase_cost, protocol_fee) = _get_purchase_payment(
+            &ctx.accounts.escrow_account,
+            _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?,
+            quantity_to_transfer,
+            config.fee_basis_points,
+        )?;
+        let seller_proceeds = purchase_cost.checked_sub(protocol_fee).ok_or(EscrowError::Overflow)?;
 
-        let purchase_cost = _get_purchase_cost(
+        // max_cost caps everything the buyer sends, including any transfer fee they bear
+        let transfer_fee = _get_buyer_transfer_fee(&ctx.accounts.escrow_account, &ctx.accounts.purchase_mint)?;
+        _check_max_cost(_get_buyer_debit(purchase_cost, protocol_fee, transfer_fee)?, max_cost)?;
+        let proceeds_before = token_interface::unpack_token_account(&ctx.accounts.seller_proceeds_account)?.amount;
+
+        // First transfer the payer's payment, split between the seller and the treasury
+        token_interface::transfer_checked(
+            ctx.accounts.purchase_token_program.clone(),
+            ctx.accounts.buy_from_account.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.seller_proceeds_account.clone(),
+            ctx.accounts.signer.to_account_info(),
+            _gross_up_for_transfer_fee(seller_proceeds, transfer_fee)?,
+            &[],
+        )?;
+        if protocol_fee > 0 {
+            _check_treasury_account(&ctx.accounts.treasury_account, &ctx.accounts.purchase_mint.key(), &config)?;
+            token_interface::transfer_checked(
+                ctx.accounts.purchase_token_program.clone(),
+                ctx.accounts.buy_from_account.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.treasury_account.clone(),
+                ctx.accounts.signer.to_account_info(),
+                _gross_up_for_transfer_fee(protocol_fee, transfer_fee)?,
+                &[],
+            )?;
+        }
+        let seller_received = token_interface::unpack_token_account(&ctx.accounts.seller_proceeds_account)?.amount.checked_sub(proceeds_before).ok_or(EscrowError::Overflow)?;
+
+        // Second transfer the asset to the recipient, creating their account if needed
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.buy_to_account.clone(),
+            ctx.accounts.recipient.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+        _record_purchase(&mut ctx.accounts.escrow_account, quantity_to_transfer, purchase_cost)?;
+        _deliver_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.buy_to_account.clone(),
+            ctx.accounts.token_program.clone(),
             quantity_to_transfer,
-            ctx.accounts.escrow_token_account.amount,
-            escrow_account.total_purchase_cost
         )?;
 
-        // First transfer the payer's payment and reduce the total cost for future
-        let transfer_ctx = CpiContext::new(ctx.accounts.token_program.clone(), token::Transfer {
-            authority: ctx.accounts.signer.to_account_info(),
-            from: ctx.accounts.buy_from_account.to_account_info(),
-            to: ctx.accounts.seller_proceeds_account.to_account_info(),
+        emit!(EscrowPurchased {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            buyer: ctx.accounts.signer.key(),
+            recipient: ctx.accounts.recipient.key(),
+            quantity: quantity_to_transfer,
+            cost: purchase_cost,
+            protocol_fee,
+            seller_received,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
         });
-        token::transfer(transfer_ctx, purchase_cost)?;
-        escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_sub(purchase_cost).ok_or(ProgramError::InsufficientFunds)?;
-
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            _ESCROW_SEED,
-            &ctx.accounts.seller_proceeds_account.key().to_bytes(),
-            &ctx.accounts.receiver.key().to_bytes(),
-            &ctx.accounts.mint.key().to_bytes(),
-            &ctx.accounts.purchase_mint.key().to_bytes(),
-            &ctx.accounts.rent_payer.key().to_bytes(),
-            &[ctx.accounts.escrow_account.bump_seed]
-            ]];
-
-        // TODO: support creating this account if it doesn't already exist
-        // Second transfer the asset to the receiver
-        let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::Transfer {
-            authority: ctx.accounts.escrow_account.to_account_info(),
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.buy_to_account.to_account_info(),
-        }, signer_seeds);
-        token::transfer(transfer_ctx, quantity_to_transfer)?;
 
         // Third close the accounts
-        ctx.accounts.escrow_token_account.reload()?;
-        if ctx.accounts.escrow_token_account.amount == 0 {
-            let close_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::CloseAccount {
-                authority: ctx.accounts.escrow_account.to_account_info(),
-                account: ctx.accounts.escrow_token_account.to_account_info(),
-                destination: ctx.accounts.rent_payer.to_account_info(),
-            }, signer_seeds);
-            token::close_account(close_ctx)?;
+        _close_escrow_if_empty(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )
+    }
 
-            ctx.accounts.escrow_account.close(ctx.accounts.rent_payer.clone())?;
-        }
+    /// Purchase as much of the escrow as max_spend affords at its current price, fees included, paying the exact cost of
+    /// that quantity
+    pub fn purchase_with_budget(ctx: Context<Purchase>, max_spend: u64) -> ProgramResult {
+        let config = _load_config(&ctx.accounts.config)?;
+        let quantity = _get_budget_quantity(
+            &ctx.accounts.escrow_account,
+            _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?,
+            max_spend,
+            config.fee_basis_points,
+            _get_buyer_transfer_fee(&ctx.accounts.escrow_account, &ctx.accounts.purchase_mint)?,
+        )?;
+        purchase_partial(ctx, quantity, max_spend)
+    }
+
+    /// Purchase the whole escrow with native SOL, failing if it costs more than max_cost lamports
+    pub fn purchase_with_sol(ctx: Context<PurchaseWithSol>, max_cost: u64) -> ProgramResult {
+        let quantity_remaining = _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?;
+        purchase_partial_with_sol(ctx, quantity_remaining, max_cost)?;
 
         Ok(())
     }
 
-    pub fn cancel(ctx: Context<Cancel>) -> ProgramResult {
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            _ESCROW_SEED,
-            &ctx.accounts.seller_proceeds_account.key().to_bytes(),
-            &ctx.accounts.receiver.key().to_bytes(),
-            &ctx.accounts.mint.key().to_bytes(),
-            &ctx.accounts.purchase_mint.key().to_bytes(),
-            &ctx.accounts.seller.key().to_bytes(),
-            &[ctx.accounts.escrow_account.bump_seed]
-            ]];
+    pub fn purchase_partial_with_sol(ctx: Context<PurchaseWithSol>, quantity_to_transfer: u64, max_cost: u64) -> ProgramResult {
+        let config = _load_config(&ctx.accounts.config)?;
+        let (purchase_cost, protocol_fee) = _get_purchase_payment(
+            &ctx.accounts.escrow_account,
+            _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?,
+            quantity_to_transfer,
+            config.fee_basis_points,
+        )?;
+        _check_max_cost(purchase_cost, max_cost)?;
+        let seller_proceeds = purchase_cost.checked_sub(protocol_fee).ok_or(EscrowError::Overflow)?;
 
-        // Return the funds from the escrow token account to the original seller
-        let transfer_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::Transfer {
-            authority: ctx.accounts.escrow_account.to_account_info(),
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.sell_from_account.to_account_info(),
-        }, signer_seeds);
-        token::transfer(transfer_ctx, ctx.accounts.escrow_token_account.amount)?;
+        // First wrap the payer's lamports straight into the seller's and the treasury's wrapped SOL accounts
+        _wrap_sol(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.seller_proceeds_account.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.purchase_token_program.clone(),
+            seller_proceeds,
+        )?;
+        if protocol_fee > 0 {
+            _check_treasury_account(&ctx.accounts.treasury_account, &ctx.accounts.purchase_mint.key(), &config)?;
+            _wrap_sol(
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.treasury_account.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.purchase_token_program.clone(),
+                protocol_fee,
+            )?;
+        }
 
-        // Close the token account
-        let close_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::CloseAccount {
-            authority: ctx.accounts.escrow_account.to_account_info(),
-            account: ctx.accounts.escrow_token_account.to_account_info(),
-            destination: ctx.accounts.seller.to_account_info(),
-        }, signer_seeds);
-        token::close_account(close_ctx)?;
+        // Second transfer the asset to the recipient, creating their account if needed
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.buy_to_account.clone(),
+            ctx.accounts.recipient.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+        _record_purchase(&mut ctx.accounts.escrow_account, quantity_to_transfer, purchase_cost)?;
+        _deliver_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.buy_to_account.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity_to_transfer,
+        )?;
+
+        emit!(EscrowPurchased {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            buyer: ctx.accounts.signer.key(),
+            recipient: ctx.accounts.recipient.key(),
+            quantity: quantity_to_transfer,
+            cost: purchase_cost,
+            protocol_fee,
+            seller_received: seller_proceeds,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+
+        // Third close the accounts
+        _close_escrow_if_empty(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )
+    }
+
+    pub fn bid(ctx: Context<Bid>, amount: u64) -> ProgramResult {
+        let (min_bid, end_time) = match ctx.accounts.escrow_account.pricing_mode {
+            PricingMode::EnglishAuction { min_bid, end_time } => (min_bid, end_time),
+            _ => return Err(EscrowError::NotAnAuction.into()),
+        };
+        if Clock::get()?.unix_timestamp >= end_time {
+            return Err(EscrowError::AuctionEnded.into());
+        }
+
+        // Lock the bid in the vault, counting only what arrives after any transfer fee
+        let vault_before = token_interface::unpack_token_account(&ctx.accounts.bid_vault)?.amount;
+        token_interface::transfer_checked(
+            ctx.accounts.purchase_token_program.clone(),
+            ctx.accounts.bidder_account.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.bid_vault.clone(),
+            ctx.accounts.bidder.to_account_info(),
+            amount,
+            &[],
+        )?;
+        let bid = token_interface::unpack_token_account(&ctx.accounts.bid_vault)?.amount.checked_sub(vault_before).ok_or(EscrowError::Overflow)?;
+        let previous_bid = ctx.accounts.escrow_account.highest_bid;
+        if bid < min_bid || bid <= previous_bid {
+            return Err(EscrowError::BidTooLow.into());
+        }
+
+        // Refund the bid this one replaces to its bidder's associated account, recreating it if it was closed so an
+        // outbid bidder can't block later bids
+        if previous_bid > 0 {
+            token_interface::create_associated_token_account_if_needed(
+                ctx.accounts.bidder.to_account_info(),
+                ctx.accounts.previous_bidder_account.clone(),
+                ctx.accounts.previous_bidder.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.purchase_token_program.clone(),
+                ctx.accounts.associated_token_program.clone(),
+                ctx.accounts.rent.to_account_info(),
+            )?;
+            _transfer_from_escrow(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.bid_vault.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.previous_bidder_account.clone(),
+                ctx.accounts.purchase_token_program.clone(),
+                previous_bid,
+            )?;
+        }
+
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        escrow_account.highest_bid = bid;
+        escrow_account.highest_bidder = ctx.accounts.bidder.key();
+        escrow_account.highest_bidder_account = token_interface::get_associated_token_address(
+            &ctx.accounts.bidder.key(),
+            &ctx.accounts.purchase_mint.key(),
+            ctx.accounts.purchase_token_program.key,
+        );
+
+        emit!(EscrowBid {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: escrow_account.escrow_id,
+            bidder: ctx.accounts.bidder.key(),
+            bid,
+            previous_bid,
+        });
+
+        Ok(())
+    }
+
+    pub fn settle(ctx: Context<Settle>) -> ProgramResult {
+        let end_time = match ctx.accounts.escrow_account.pricing_mode {
+            PricingMode::EnglishAuction { end_time, .. } => end_time,
+            _ => return Err(EscrowError::NotAnAuction.into()),
+        };
+        if Clock::get()?.unix_timestamp < end_time {
+            return Err(EscrowError::AuctionNotEnded.into());
+        }
+        let winning_bid = ctx.accounts.escrow_account.highest_bid;
+        if winning_bid == 0 {
+            return Err(EscrowError::NoBids.into());
+        }
+
+        // First pay out the vault, split between the seller and the treasury. The seller takes the whole balance so
+        // that tokens sent to the vault directly can't stop it from closing
+        let vault_balance = token_interface::unpack_token_account(&ctx.accounts.bid_vault)?.amount;
+        let config = _load_config(&ctx.accounts.config)?;
+        let protocol_fee = _get_protocol_fee(winning_bid, config.fee_basis_points)?;
+        let seller_proceeds = vault_balance.checked_sub(protocol_fee).ok_or(EscrowError::Overflow)?;
+        _transfer_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.bid_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.seller_proceeds_account.clone(),
+            ctx.accounts.purchase_token_program.clone(),
+            seller_proceeds,
+        )?;
+        if protocol_fee > 0 {
+            _check_treasury_account(&ctx.accounts.treasury_account, &ctx.accounts.purchase_mint.key(), &config)?;
+            _transfer_from_escrow(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.bid_vault.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.treasury_account.clone(),
+                ctx.accounts.purchase_token_program.clone(),
+                protocol_fee,
+            )?;
+        }
+        _close_escrow_token_account(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.bid_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.purchase_token_program.clone(),
+        )?;
+
+        // Second transfer the asset to the winner, creating their account if needed
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.winner_token_account.clone(),
+            ctx.accounts.winner.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+        let quantity = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        _record_purchase(&mut ctx.accounts.escrow_account, quantity, winning_bid)?;
+        _transfer_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.winner_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity,
+        )?;
+
+        emit!(EscrowSettled {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            winner: ctx.accounts.winner.key(),
+            quantity,
+            winning_bid,
+            protocol_fee,
+        });
+
+        // Third close the accounts
+        _close_escrow_if_empty(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )
+    }
+
+    /// Commit to a sealed bid of sha256(escrow, bidder, bid as little-endian u64, salt), locking a deposit that the bid
+    /// must not exceed
+    pub fn commit_bid(ctx: Context<CommitBid>, commitment: [u8; 32], deposit: u64) -> ProgramResult {
+        let reveal_end_time = match ctx.accounts.escrow_account.pricing_mode {
+            PricingMode::SealedBidAuction { commit_end_time, reveal_end_time, .. } => {
+                if Clock::get()?.unix_timestamp >= commit_end_time {
+                    return Err(EscrowError::NotInCommitPhase.into());
+                }
+                reveal_end_time
+            },
+            _ => return Err(EscrowError::NotAnAuction.into()),
+        };
+
+        // Lock the deposit in the commitment's own vault, counting only what arrives after any transfer fee
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.bidder.to_account_info(),
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.bid_commitment.to_account_info(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.purchase_token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+        let vault_before = token_interface::unpack_token_account(&ctx.accounts.deposit_vault)?.amount;
+        token_interface::transfer_checked(
+            ctx.accounts.purchase_token_program.clone(),
+            ctx.accounts.bidder_account.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.bidder.to_account_info(),
+            deposit,
+            &[],
+        )?;
+        let deposit_received = token_interface::unpack_token_account(&ctx.accounts.deposit_vault)?.amount.checked_sub(vault_before).ok_or(EscrowError::Overflow)?;
+        if deposit_received == 0 {
+            return Err(EscrowError::ZeroCost.into());
+        }
+
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        escrow_account.commitment_count = escrow_account.commitment_count.checked_add(1).ok_or(EscrowError::Overflow)?;
+
+        let bid_commitment = &mut ctx.accounts.bid_commitment;
+        bid_commitment.escrow = ctx.accounts.escrow_account.key();
+        bid_commitment.bidder = ctx.accounts.bidder.key();
+        bid_commitment.bidder_account = ctx.accounts.bidder_account.key();
+        bid_commitment.seller_proceeds_account = ctx.accounts.escrow_account.seller_proceeds_account;
+        bid_commitment.commitment = commitment;
+        bid_commitment.deposit = deposit_received;
+        bid_commitment.reveal_end_time = reveal_end_time;
+        bid_commitment.bump_seed = *ctx.bumps.get("bid_commitment").unwrap();
+
+        emit!(EscrowBidCommitted {
+            escrow: ctx.accounts.escrow_account.key(),
+            bidder: ctx.accounts.bidder.key(),
+            deposit: deposit_received,
+        });
+
+        Ok(())
+    }
+
+    pub fn reveal_bid(ctx: Context<RevealBid>, bid: u64, salt: [u8; 32]) -> ProgramResult {
+        let min_bid = match ctx.accounts.escrow_account.pricing_mode {
+            PricingMode::SealedBidAuction { min_bid, commit_end_time, reveal_end_time } => {
+                let now = Clock::get()?.unix_timestamp;
+                if now < commit_end_time || now >= reveal_end_time {
+                    return Err(EscrowError::NotInRevealPhase.into());
+                }
+                min_bid
+            },
+            _ => return Err(EscrowError::NotAnAuction.into()),
+        };
+
+        let bid_commitment = &mut ctx.accounts.bid_commitment;
+        if bid_commitment.revealed_bid != 0 {
+            return Err(EscrowError::AlreadyRevealed.into());
+        }
+        if _get_bid_commitment(&bid_commitment.escrow, &bid_commitment.bidder, bid, &salt) != bid_commitment.commitment {
+            return Err(EscrowError::InvalidReveal.into());
+        }
+        if bid > bid_commitment.deposit {
+            return Err(EscrowError::BidExceedsDeposit.into());
+        }
+        if bid < min_bid {
+            return Err(EscrowError::BidTooLow.into());
+        }
+        bid_commitment.revealed_bid = bid;
+
+        // Ties go to whoever revealed first
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        if bid > escrow_account.highest_bid {
+            escrow_account.highest_bid = bid;
+            escrow_account.highest_bidder = bid_commitment.bidder;
+            escrow_account.highest_bidder_account = bid_commitment.bidder_account;
+        }
+
+        emit!(EscrowBidRevealed {
+            escrow: escrow_account.key(),
+            bidder: bid_commitment.bidder,
+            bid,
+            highest_bid: escrow_account.highest_bid,
+        });
+
+        Ok(())
+    }
+
+    /// Return a deposit that didn't win once the reveal window has ended. A deposit whose bid was never revealed is
+    /// forfeited to the seller, so committing can't be used to bid without being bound by it
+    pub fn refund_bid(ctx: Context<RefundBid>) -> ProgramResult {
+        if Clock::get()?.unix_timestamp < ctx.accounts.bid_commitment.reveal_end_time {
+            return Err(EscrowError::AuctionNotEnded.into());
+        }
+
+        // Until the auction settles, the winning deposit is still owed to the seller. Once it settles the escrow is
+        // closed, or replaced by a later listing with its own reveal window. The escrow can't be closed any other way
+        // while deposits are outstanding, so a deposit returned after it closes was never at risk of a cancelled sale
+        if let Ok(mut escrow_account) = Account::<EscrowAccount>::try_from(&ctx.accounts.escrow_account) {
+            let same_auction = matches!(escrow_account.pricing_mode,
+                PricingMode::SealedBidAuction { reveal_end_time, .. } if reveal_end_time == ctx.accounts.bid_commitment.reveal_end_time);
+            if same_auction && escrow_account.commitment_count > 0 {
+                if escrow_account.highest_bid > 0 && escrow_account.highest_bidder == ctx.accounts.bid_commitment.bidder {
+                    return Err(EscrowError::WinningBidNotRefundable.into());
+                }
+                escrow_account.commitment_count -= 1;
+                escrow_account.exit(ctx.program_id)?;
+            }
+        }
+
+        let deposit = token_interface::unpack_token_account(&ctx.accounts.deposit_vault)?.amount;
+        let forfeited = ctx.accounts.bid_commitment.revealed_bid == 0;
+        let destination = if forfeited {
+            ctx.accounts.seller_proceeds_account.clone()
+        } else {
+            ctx.accounts.bidder_account.clone()
+        };
+        _transfer_from_bid_deposit(
+            &ctx.accounts.bid_commitment,
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            destination,
+            ctx.accounts.purchase_token_program.clone(),
+            deposit,
+        )?;
+        _close_bid_deposit(
+            &ctx.accounts.bid_commitment,
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.bidder.clone(),
+            ctx.accounts.purchase_token_program.clone(),
+        )?;
+
+        if forfeited {
+            emit!(EscrowBidForfeited {
+                escrow: ctx.accounts.bid_commitment.escrow,
+                bidder: ctx.accounts.bid_commitment.bidder,
+                deposit,
+            });
+        } else {
+            emit!(EscrowBidRefunded {
+                escrow: ctx.accounts.bid_commitment.escrow,
+                bidder: ctx.accounts.bid_commitment.bidder,
+                refund: deposit,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn settle_sealed(ctx: Context<SettleSealed>) -> ProgramResult {
+        let reveal_end_time = match ctx.accounts.escrow_account.pricing_mode {
+            PricingMode::SealedBidAuction { reveal_end_time, .. } => reveal_end_time,
+            _ => return Err(EscrowError::NotAnAuction.into()),
+        };
+        if Clock::get()?.unix_timestamp < reveal_end_time {
+            return Err(EscrowError::AuctionNotEnded.into());
+        }
+        let winning_bid = ctx.accounts.escrow_account.highest_bid;
+        if winning_bid == 0 {
+            return Err(EscrowError::NoBids.into());
+        }
+
+        // First pay the winning bid out of the winner's deposit, split between the seller and the treasury, and
+        // refund the rest of the deposit to the winner
+        let config = _load_config(&ctx.accounts.config)?;
+        let protocol_fee = _get_protocol_fee(winning_bid, config.fee_basis_points)?;
+        let seller_proceeds = winning_bid.checked_sub(protocol_fee).ok_or(EscrowError::Overflow)?;
+        _transfer_from_bid_deposit(
+            &ctx.accounts.bid_commitment,
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.seller_proceeds_account.clone(),
+            ctx.accounts.purchase_token_program.clone(),
+            seller_proceeds,
+        )?;
+        if protocol_fee > 0 {
+            _check_treasury_account(&ctx.accounts.treasury_account, &ctx.accounts.purchase_mint.key(), &config)?;
+            _transfer_from_bid_deposit(
+                &ctx.accounts.bid_commitment,
+                ctx.accounts.deposit_vault.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.treasury_account.clone(),
+                ctx.accounts.purchase_token_program.clone(),
+                protocol_fee,
+            )?;
+        }
+        let refund = token_interface::unpack_token_account(&ctx.accounts.deposit_vault)?.amount;
+        if refund > 0 {
+            _transfer_from_bid_deposit(
+                &ctx.accounts.bid_commitment,
+                ctx.accounts.deposit_vault.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.winner_bidder_account.clone(),
+                ctx.accounts.purchase_token_program.clone(),
+                refund,
+            )?;
+        }
+        _close_bid_deposit(
+            &ctx.accounts.bid_commitment,
+            ctx.accounts.deposit_vault.clone(),
+            ctx.accounts.purchase_mint.clone(),
+            ctx.accounts.winner.clone(),
+            ctx.accounts.purchase_token_program.clone(),
+        )?;
+
+        // Second transfer the asset to the winner, creating their account if needed
+        token_interface::create_associated_token_account_if_needed(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.winner_token_account.clone(),
+            ctx.accounts.winner.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.clone(),
+            ctx.accounts.associated_token_program.clone(),
+            ctx.accounts.rent.to_account_info(),
+        )?;
+        let quantity = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        _record_purchase(&mut ctx.accounts.escrow_account, quantity, winning_bid)?;
+        _transfer_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.winner_token_account.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity,
+        )?;
+
+        emit!(EscrowSettled {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            winner: ctx.accounts.winner.key(),
+            quantity,
+            winning_bid,
+            protocol_fee,
+        });
+
+        // Third close the accounts
+        _close_escrow_if_empty(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )
+    }
+
+    pub fn cancel(ctx: Context<Cancel>) -> ProgramResult {
+        // Once bids are committed the auction must run its course, and can only be cancelled once every deposit has
+        // been returned. Otherwise the seller could pocket the unrevealed deposits without ever selling
+        if ctx.accounts.escrow_account.commitment_count > 0 {
+            return Err(EscrowError::AuctionHasCommitments.into());
+        }
+
+        // Return the funds from the escrow token account to the original seller
+        let quantity_returned = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        _transfer_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity_returned,
+        )?;
+
+        // Close the token account
+        _close_escrow_token_account(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.seller.to_account_info(),
+            ctx.accounts.token_program.clone(),
+        )?;
+        _release_mint_authority(&ctx.accounts.escrow_account, ctx.accounts.mint.clone(), ctx.accounts.token_program.clone())?;
+
+        // An English auction without bids still holds its bid vault. Anything sent to it directly goes to the seller
+        if let PricingMode::EnglishAuction { .. } = ctx.accounts.escrow_account.pricing_mode {
+            let vault_balance = token_interface::unpack_token_account(&ctx.accounts.bid_vault)?.amount;
+            if vault_balance > 0 {
+                _transfer_from_escrow(
+                    &ctx.accounts.escrow_account,
+                    ctx.accounts.bid_vault.clone(),
+                    ctx.accounts.purchase_mint.clone(),
+                    ctx.accounts.seller_proceeds_account.clone(),
+                    ctx.accounts.purchase_token_program.clone(),
+                    vault_balance,
+                )?;
+            }
+            _close_escrow_token_account(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.bid_vault.clone(),
+                ctx.accounts.purchase_mint.clone(),
+                ctx.accounts.seller.to_account_info(),
+                ctx.accounts.purchase_token_program.clone(),
+            )?;
+        }
+
+        emit!(EscrowCancelled {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: quantity_returned,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+        emit!(EscrowClosed {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.seller.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+        });
+
+        Ok(())
+    }
+
+    /// Reprice the remaining lots of a fixed price escrow without delisting it
+    pub fn update_price(ctx: Context<UpdatePrice>, price_per_lot: u64) -> ProgramResult {
+        if price_per_lot == 0 {
+            return Err(EscrowError::ZeroCost.into());
+        }
+        let escrow_quantity = _get_escrow_quantity(&ctx.accounts.escrow_account, &ctx.accounts.escrow_token_account)?;
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        _check_not_expired(escrow_account, &Clock::get()?)?;
+
+        let old_price_per_lot = escrow_account.price_per_lot;
+        let old_total_purchase_cost = escrow_account.total_purchase_cost;
+        escrow_account.total_purchase_cost = _get_rounded_cost(escrow_quantity, escrow_account.lot_size, price_per_lot, escrow_account.rounding)?;
+        escrow_account.price_per_lot = price_per_lot;
+
+        emit!(EscrowRepriced {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: escrow_account.seller_proceeds_account,
+            receiver: escrow_account.receiver,
+            mint: escrow_account.mint,
+            purchase_mint: escrow_account.purchase_mint,
+            rent_payer: escrow_account.rent_payer,
+            escrow_id: escrow_account.escrow_id,
+            old_price_per_lot,
+            price_per_lot,
+            old_total_purchase_cost,
+            total_purchase_cost: escrow_account.total_purchase_cost,
+        });
+
+        Ok(())
+    }
+
+    /// Return part of a fixed price escrow to the seller, reducing its total cost by what that part would have sold for
+    pub fn withdraw_partial(ctx: Context<WithdrawPartial>, quantity: u64) -> ProgramResult {
+        let escrow_quantity = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        let escrow_account = &mut ctx.accounts.escrow_account;
+        let cost = _get_purchase_cost(escrow_account, quantity, escrow_quantity)?;
+
+        // The withdrawn tokens were never sold, so they no longer count as tendered
+        escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_sub(cost).ok_or(EscrowError::Overflow)?;
+        escrow_account.original_quantity = escrow_account.original_quantity.checked_sub(quantity).ok_or(EscrowError::Overflow)?;
+        _transfer_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.sell_from_account.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity,
+        )?;
+
+        emit!(EscrowWithdrawn {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.escrow_account.seller_proceeds_account,
+            receiver: ctx.accounts.escrow_account.receiver,
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.escrow_account.purchase_mint,
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity,
+            cost,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+
+        _close_escrow_if_empty(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )
+    }
+
+    /// Cancel an escrow created by tender_from_mint, burning its tokens or returning them to destination. Its seller
+    /// needn't hold any of the tokens, so the rent payer or the mint's authority cancels it instead
+    pub fn cancel_minted(ctx: Context<CancelMinted>, burn_supply: bool) -> ProgramResult {
+        let escrow_account = &ctx.accounts.escrow_account;
+        let quantity = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        if burn_supply {
+            _burn_from_escrow(
+                escrow_account,
+                ctx.accounts.escrow_token_account.clone(),
+                ctx.accounts.mint.clone(),
+                ctx.accounts.token_program.clone(),
+                quantity,
+            )?;
+        } else {
+            if token_interface::token_account_mint(&ctx.accounts.destination) != Some(ctx.accounts.mint.key()) {
+                return Err(EscrowError::WrongMint.into());
+            }
+            _transfer_from_escrow(
+                escrow_account,
+                ctx.accounts.escrow_token_account.clone(),
+                ctx.accounts.mint.clone(),
+                ctx.accounts.destination.clone(),
+                ctx.accounts.token_program.clone(),
+                quantity,
+            )?;
+        }
+        _close_escrow_token_account(
+            escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.rent_payer.clone(),
+            ctx.accounts.token_program.clone(),
+        )?;
+
+        emit!(EscrowCancelled {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: escrow_account.seller_proceeds_account,
+            receiver: escrow_account.receiver,
+            mint: escrow_account.mint,
+            purchase_mint: escrow_account.purchase_mint,
+            rent_payer: escrow_account.rent_payer,
+            escrow_id: escrow_account.escrow_id,
+            quantity,
+            total_purchase_cost: escrow_account.total_purchase_cost,
+        });
+        emit!(EscrowClosed {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: escrow_account.seller_proceeds_account,
+            receiver: escrow_account.receiver,
+            mint: escrow_account.mint,
+            purchase_mint: escrow_account.purchase_mint,
+            rent_payer: escrow_account.rent_payer,
+            escrow_id: escrow_account.escrow_id,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a lazy mint escrow, handing the mint authority back to whoever held it before. Nothing was minted into
+    /// the escrow, so the rent payer or that original authority cancels it
+    pub fn cancel_lazy_mint(ctx: Context<CancelLazyMint>) -> ProgramResult {
+        let escrow_account = &ctx.accounts.escrow_account;
+        _release_mint_authority(escrow_account, ctx.accounts.mint.clone(), ctx.accounts.token_program.clone())?;
+
+        emit!(EscrowCancelled {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: escrow_account.seller_proceeds_account,
+            receiver: escrow_account.receiver,
+            mint: escrow_account.mint,
+            purchase_mint: escrow_account.purchase_mint,
+            rent_payer: escrow_account.rent_payer,
+            escrow_id: escrow_account.escrow_id,
+            quantity: escrow_account.original_quantity.checked_sub(escrow_account.quantity_sold).ok_or(EscrowError::Overflow)?,
+            total_purchase_cost: escrow_account.total_purchase_cost,
+        });
+        emit!(EscrowClosed {
+            escrow: escrow_account.key(),
+            seller_proceeds_account: escrow_account.seller_proceeds_account,
+            receiver: escrow_account.receiver,
+            mint: escrow_account.mint,
+            purchase_mint: escrow_account.purchase_mint,
+            rent_payer: escrow_account.rent_payer,
+            escrow_id: escrow_account.escrow_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_expired(ctx: Context<CloseExpired>) -> ProgramResult {
+        match ctx.accounts.escrow_account.expires_at {
+            Some(expires_at) if Clock::get()?.unix_timestamp >= expires_at => {},
+            _ => return Err(EscrowError::EscrowNotExpired.into()),
+        }
+
+        // Return the funds from the escrow token account to the seller. A lazy mint escrow holds none, and has no
+        // token account
+        let mut quantity_returned = 0;
+        if ctx.accounts.escrow_account.lazy_mint_authority.is_none() {
+            quantity_returned = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+            _transfer_from_escrow(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.escrow_token_account.clone(),
+                ctx.accounts.mint.clone(),
+                ctx.accounts.sell_from_account.clone(),
+                ctx.accounts.token_program.clone(),
+                quantity_returned,
+            )?;
+
+            // Close the token account, returning the rent to whoever paid it
+            _close_escrow_token_account(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.escrow_token_account.clone(),
+                ctx.accounts.mint.clone(),
+                ctx.accounts.rent_payer.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+        }
+        _release_mint_authority(&ctx.accounts.escrow_account, ctx.accounts.mint.clone(), ctx.accounts.token_program.clone())?;
+
+        emit!(EscrowExpired {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity: quantity_returned,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+        emit!(EscrowClosed {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn burn(ctx: Context<Burn>, quantity: u64) -> ProgramResult {
+        if quantity == 0 {
+            return Err(EscrowError::ZeroQuantity.into());
+        }
+        let escrow_quantity = token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount;
+        if quantity > escrow_quantity {
+            return Err(EscrowError::QuantityExceedsEscrow.into());
+        }
+
+        // The burned tokens will never be sold, so a fixed price escrow no longer charges for them
+        if ctx.accounts.escrow_account.pricing_mode == PricingMode::Fixed {
+            let cost = _get_purchase_cost(&ctx.accounts.escrow_account, quantity, escrow_quantity)?;
+            let escrow_account = &mut ctx.accounts.escrow_account;
+            escrow_account.total_purchase_cost = escrow_account.total_purchase_cost.checked_sub(cost).ok_or(EscrowError::Overflow)?;
+        }
+
+        // Burn the tokens
+        _burn_from_escrow(
+            &ctx.accounts.escrow_account,
+            ctx.accounts.escrow_token_account.clone(),
+            ctx.accounts.mint.clone(),
+            ctx.accounts.token_program.clone(),
+            quantity,
+        )?;
+
+        emit!(EscrowBurned {
+            escrow: ctx.accounts.escrow_account.key(),
+            seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+            receiver: ctx.accounts.receiver.key(),
+            mint: ctx.accounts.mint.key(),
+            purchase_mint: ctx.accounts.purchase_mint.key(),
+            rent_payer: ctx.accounts.rent_payer.key(),
+            escrow_id: ctx.accounts.escrow_account.escrow_id,
+            quantity,
+            total_purchase_cost: ctx.accounts.escrow_account.total_purchase_cost,
+        });
+
+        if token_interface::unpack_token_account(&ctx.accounts.escrow_token_account)?.amount == 0 {
+            _close_escrow_token_account(
+                &ctx.accounts.escrow_account,
+                ctx.accounts.escrow_token_account.clone(),
+                ctx.accounts.mint.clone(),
+                ctx.accounts.rent_payer.to_account_info(),
+                ctx.accounts.token_program.clone(),
+            )?;
+
+            ctx.accounts.escrow_account.close(ctx.accounts.rent_payer.to_account_info())?;
+
+            emit!(EscrowClosed {
+                escrow: ctx.accounts.escrow_account.key(),
+                seller_proceeds_account: ctx.accounts.seller_proceeds_account.key(),
+                receiver: ctx.accounts.receiver.key(),
+                mint: ctx.accounts.mint.key(),
+                purchase_mint: ctx.accounts.purchase_mint.key(),
+                rent_payer: ctx.accounts.rent_payer.key(),
+                escrow_id: ctx.accounts.escrow_account.escrow_id,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_basis_points: u16, treasury: Pubkey) -> ProgramResult {
+        _check_fee_basis_points(fee_basis_points)?;
+
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.fee_basis_points = fee_basis_points;
+        config.treasury = treasury;
+        config.bump_seed = *ctx.bumps.get("config").unwrap();
+
+        Ok(())
+    }
+
+    pub fn set_fee(ctx: Context<UpdateConfig>, fee_basis_points: u16) -> ProgramResult {
+        _check_fee_basis_points(fee_basis_points)?;
+        ctx.accounts.config.fee_basis_points = fee_basis_points;
+
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<UpdateConfig>, treasury: Pubkey) -> ProgramResult {
+        ctx.accounts.config.treasury = treasury;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct Tender<'info> {
+    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer, escrow_id]
+    #[account(init_if_needed,
+        payer = seller,
+        space = 8 + EscrowAccount::LEN,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), seller.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account in which to store the tokens. Must be the associated token account for the escrow_account's public key, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The seller who is creating this escrow account. The seller must be the signer of this transaction
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made. Pass OPEN_LISTING_RECEIVER to let anyone purchase
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. May be owned by either token program
+    #[account(constraint = token_interface::is_token_program(purchase_mint.owner) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the proceeds will be transferred
+    #[account(
+        constraint = token_interface::token_account_mint(&seller_proceeds_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&seller_proceeds_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The seller's token account from which the tokens for sale will be trasnferred to create the escrow
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&sell_from_account) == Some(mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&sell_from_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub sell_from_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct TenderEnglish<'info> {
+    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer, escrow_id]
+    #[account(init_if_needed,
+        payer = seller,
+        space = 8 + EscrowAccount::LEN,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), seller.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account in which to store the tokens. Must be the associated token account for the escrow_account's public key, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+    /// The account in which to lock the highest bid. Must be the escrow_account's associated token account for the purchase_mint, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub bid_vault: AccountInfo<'info>,
+
+    /// The seller who is creating this escrow account. The seller must be the signer of this transaction
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    /// The only user allowed to bid. Pass OPEN_LISTING_RECEIVER to let anyone bid
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the winning bid will be transferred
+    #[account(
+        constraint = token_interface::token_account_mint(&seller_proceeds_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&seller_proceeds_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The seller's token account from which the tokens for sale will be trasnferred to create the escrow
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&sell_from_account) == Some(mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&sell_from_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub sell_from_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct TenderFromMint<'info> {
+    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer, escrow_id]
+    #[account(init_if_needed,
+        payer = payer,
+        space = 8 + EscrowAccount::LEN,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), payer.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account in which to store the tokens. Must be the associated token account for the escrow_account's public key, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The mint_authority who is creating this escrow account. Must be the signer of this transaction
+    #[account(mut)]
+    pub mint_authority: AccountInfo<'info>,
+    /// The account that will fund the creation of the escrow and token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made. Pass OPEN_LISTING_RECEIVER to let anyone purchase
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. May be owned by either token program
+    #[account(constraint = token_interface::is_token_program(purchase_mint.owner) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the proceeds will be transferred
+    #[account(constraint = token_interface::token_account_mint(&seller_proceeds_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct TenderFromMintSigned<'info> {
+    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer, escrow_id]
+    #[account(init_if_needed,
+        payer = payer,
+        space = 8 + EscrowAccount::LEN,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), payer.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account in which to store the tokens. Must be the associated token account for the escrow_account's public key, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The mint's authority, which must sign directly. A program's PDA signs through invoke_signed
+    #[account(constraint = token_interface::mint_authority(&mint) == Some(mint_authority.key()) @ EscrowError::WrongMintAuthority)]
+    pub mint_authority: Signer<'info>,
+    /// The account that will fund the creation of the escrow and token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made. Pass OPEN_LISTING_RECEIVER to let anyone purchase
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. May be owned by either token program
+    #[account(constraint = token_interface::is_token_program(purchase_mint.owner) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the proceeds will be transferred
+    #[account(constraint = token_interface::token_account_mint(&seller_proceeds_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct TenderLazyMint<'info> {
+    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer, escrow_id].
+    /// Nobody but the escrow can sign for the mint once it is tendered, so it can't be tendered into again to raise the cap
+    #[account(init,
+        payer = payer,
+        space = 8 + EscrowAccount::LEN,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), payer.key().as_ref(), escrow_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+
+    /// The mint's current authority, which hands it to the escrow. Must sign unless it is a multisig, whose signers are
+    /// passed as remaining accounts, or already the escrow
+    pub mint_authority: AccountInfo<'info>,
+    /// The account that will fund the creation of the escrow
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made. Pass OPEN_LISTING_RECEIVER to let anyone purchase
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. May be owned by either token program
+    #[account(constraint = token_interface::is_token_program(purchase_mint.owner) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the proceeds will be transferred
+    #[account(constraint = token_interface::token_account_mint(&seller_proceeds_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Purchase<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The person who paid to create the account and will receive the rent back
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made, or OPEN_LISTING_RECEIVER for open listings
+    pub receiver: AccountInfo<'info>,
+    /// The wallet that will own the buy_to_account. Must be the receiver, unless this is an open listing
+    #[account(constraint = (receiver.key() == OPEN_LISTING_RECEIVER || recipient.key() == receiver.key()) @ EscrowError::WrongOwner)]
+    pub recipient: AccountInfo<'info>,
+    /// The person paying to release the tokens from escrow. Must be the signer and own the buy_from_account.
+    /// Also pays to create the buy_to_account if it doesn't already exist
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// The mint account for the token in escrow, which a lazy mint escrow mints from. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. Must be owned by the purchase_token_program
+    #[account(constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the proceeds will be transferred
+    #[account(mut)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The treasury's token account into which the protocol fee will be transferred. Only checked when a fee is owed, so
+    /// any account may be passed while the fee is zero
+    #[account(mut)]
+    pub treasury_account: AccountInfo<'info>,
+    /// The signer's token account which will pay the purchase price
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&buy_from_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&buy_from_account) == Some(signer.key()) @ EscrowError::WrongOwner,
+    )]
+    pub buy_from_account: AccountInfo<'info>,
+    /// The recipient's associated token account into which the asset for sale will be deposited. Created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&recipient.key(), &mint.key(), token_program.key))]
+    pub buy_to_account: AccountInfo<'info>,
+
+    /// The global configuration holding the protocol fee. No fee is taken if it hasn't been created
+    #[account(seeds = [_CONFIG_SEED], bump)]
+    pub config: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseWithSol<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The person who paid to create the account and will receive the rent back
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made, or OPEN_LISTING_RECEIVER for open listings
+    pub receiver: AccountInfo<'info>,
+    /// The wallet that will own the buy_to_account. Must be the receiver, unless this is an open listing
+    #[account(constraint = (receiver.key() == OPEN_LISTING_RECEIVER || recipient.key() == receiver.key()) @ EscrowError::WrongOwner)]
+    pub recipient: AccountInfo<'info>,
+    /// The person paying to release the tokens from escrow. Must be the signer and pays the purchase price in lamports.
+    /// Also pays to create the buy_to_account if it doesn't already exist
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// The mint account for the token in escrow, which a lazy mint escrow mints from. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. Must be the purchase_token_program's native (wrapped SOL) mint
+    #[account(
+        constraint = token_interface::is_native_mint(&purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram,
+    )]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's wrapped SOL account into which the proceeds will be deposited
+    #[account(mut)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The treasury's wrapped SOL account into which the protocol fee will be deposited. Only checked when a fee is owed, so
+    /// any account may be passed while the fee is zero
+    #[account(mut)]
+    pub treasury_account: AccountInfo<'info>,
+    /// The recipient's associated token account into which the asset for sale will be deposited. Created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&recipient.key(), &mint.key(), token_program.key))]
+    pub buy_to_account: AccountInfo<'info>,
+
+    /// The global configuration holding the protocol fee. No fee is taken if it hasn't been created
+    #[account(seeds = [_CONFIG_SEED], bump)]
+    pub config: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Bid<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that locks the highest bid. Must be the escrow_account's associated token account for the purchase_mint
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub bid_vault: AccountInfo<'info>,
+
+    /// The person who paid to create the account
+    pub rent_payer: AccountInfo<'info>,
+    /// The only user allowed to bid, or OPEN_LISTING_RECEIVER if anyone may
+    pub receiver: AccountInfo<'info>,
+    /// The person placing the bid. Must be the signer and own the bidder_account. Pays to recreate the
+    /// previous_bidder_account if it has been closed
+    #[account(mut, constraint = (receiver.key() == OPEN_LISTING_RECEIVER || bidder.key() == receiver.key()) @ EscrowError::WrongOwner)]
+    pub bidder: Signer<'info>,
+    /// The wallet that placed the bid being replaced. Ignored if there is no bid yet
+    #[account(constraint = (escrow_account.highest_bid == 0 || previous_bidder.key() == escrow_account.highest_bidder) @ EscrowError::WrongOwner)]
+    pub previous_bidder: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the winning bid will be transferred
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The bidder's token account which the bid is taken from
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&bidder_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&bidder_account) == Some(bidder.key()) @ EscrowError::WrongOwner,
+    )]
+    pub bidder_account: AccountInfo<'info>,
+    /// The previous_bidder's associated token account, which is refunded and created if it doesn't already exist.
+    /// Ignored if there is no bid yet
+    #[account(mut, constraint = (escrow_account.highest_bid == 0 || previous_bidder_account.key() == escrow_account.highest_bidder_account) @ EscrowError::WrongOwner)]
+    pub previous_bidder_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+    /// The account that locks the winning bid. Must be the escrow_account's associated token account for the purchase_mint
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub bid_vault: AccountInfo<'info>,
+
+    /// The person who paid to create the account and will receive the rent back
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// The only user allowed to bid, or OPEN_LISTING_RECEIVER if anyone may
+    pub receiver: AccountInfo<'info>,
+    /// The highest bidder, who will own the winner_token_account
+    #[account(constraint = winner.key() == escrow_account.highest_bidder @ EscrowError::WrongOwner)]
+    pub winner: AccountInfo<'info>,
+    /// Anyone may settle an ended auction. Pays to create the winner_token_account if it doesn't already exist
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(mut, constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+
+    /// The seller's token account into which the winning bid will be transferred
+    #[account(mut)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The treasury's token account into which the protocol fee will be transferred. Only checked when a fee is owed, so
+    /// any account may be passed while the fee is zero
+    #[account(mut)]
+    pub treasury_account: AccountInfo<'info>,
+    /// The winner's associated token account into which the asset for sale will be deposited. Created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&winner.key(), &mint.key(), token_program.key))]
+    pub winner_token_account: AccountInfo<'info>,
+
+    /// The global configuration holding the protocol fee. No fee is taken if it hasn't been created
+    #[account(seeds = [_CONFIG_SEED], bump)]
+    pub config: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CommitBid<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut, has_one = receiver, has_one = purchase_mint)]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account in which to store the commitment. This must be a PDA with seeds ["bid", escrow_account, bidder], so each bidder commits once
+    #[account(init,
+        payer = bidder,
+        seeds = [_BID_SEED, escrow_account.key().as_ref(), bidder.key().as_ref()],
+        bump,
+    )]
+    pub bid_commitment: Account<'info, BidCommitment>,
+    /// The account in which to lock the deposit. Must be the bid_commitment's associated token account for the purchase_mint, and is created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&bid_commitment.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub deposit_vault: AccountInfo<'info>,
+
+    /// The only user allowed to bid, or OPEN_LISTING_RECEIVER if anyone may
+    pub receiver: AccountInfo<'info>,
+    /// The person committing the bid. Must be the signer and own the bidder_account
+    #[account(mut, constraint = (receiver.key() == OPEN_LISTING_RECEIVER || bidder.key() == receiver.key()) @ EscrowError::WrongOwner)]
+    pub bidder: Signer<'info>,
+
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
+    /// The bidder's token account which the deposit is taken from, and refunded to
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&bidder_account) == Some(purchase_mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&bidder_account) == Some(bidder.key()) @ EscrowError::WrongOwner,
+    )]
+    pub bidder_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
+    #[account(address=associated_token::ID)]
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct RevealBid<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut)]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The bidder's commitment to this escrow
+    #[account(mut,
+        has_one = bidder,
+        seeds = [_BID_SEED, escrow_account.key().as_ref(), bidder.key().as_ref()],
+        bump = bid_commitment.bump_seed,
+    )]
+    pub bid_commitment: Account<'info, BidCommitment>,
+    /// The person who committed the bid. Must be the signer
+    pub bidder: Signer<'info>,
+}
 
-    pub fn burn(ctx: Context<Burn>, quantity: u64) -> ProgramResult {
-        if quantity == 0 || quantity > ctx.accounts.escrow_token_account.amount {
-            return Err(ProgramError::InvalidArgument);
-        }
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            _ESCROW_SEED,
-            &ctx.accounts.seller_proceeds_account.key().to_bytes(),
-            &ctx.accounts.receiver.key().to_bytes(),
-            &ctx.accounts.mint.key().to_bytes(),
-            &ctx.accounts.purchase_mint.key().to_bytes(),
-            &ctx.accounts.rent_payer.key().to_bytes(),
-            &[ctx.accounts.escrow_account.bump_seed]
-            ]];
+#[derive(Accounts)]
+pub struct RefundBid<'info> {
+    /// The escrow the bid was for, whose count of outstanding deposits is reduced. It may already have been closed
+    #[account(mut)]
+    pub escrow_account: AccountInfo<'info>,
+    /// The bidder's commitment, which is closed and its rent returned to the bidder
+    #[account(mut,
+        close = bidder,
+        has_one = bidder,
+        has_one = bidder_account,
+        has_one = seller_proceeds_account,
+        seeds = [_BID_SEED, escrow_account.key().as_ref(), bidder.key().as_ref()],
+        bump = bid_commitment.bump_seed,
+    )]
+    pub bid_commitment: Account<'info, BidCommitment>,
+    /// The account that holds the deposit. Must be the bid_commitment's associated token account for the purchase_mint
+    #[account(mut, address=token_interface::get_associated_token_address(&bid_commitment.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub deposit_vault: AccountInfo<'info>,
 
-        // Burn the tokens
-        let burn_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::Burn {
-            mint: ctx.accounts.mint.to_account_info(),
-            to: ctx.accounts.escrow_token_account.to_account_info(),
-            authority: ctx.accounts.escrow_account.to_account_info(),
-        }, signer_seeds);
-        token::burn(burn_ctx, quantity)?;
-
-        ctx.accounts.escrow_token_account.reload()?;
-        if ctx.accounts.escrow_token_account.amount == 0 {
-            let close_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.clone(), token::CloseAccount {
-                authority: ctx.accounts.escrow_account.to_account_info(),
-                account: ctx.accounts.escrow_token_account.to_account_info(),
-                destination: ctx.accounts.rent_payer.to_account_info(),
-            }, signer_seeds);
-            token::close_account(close_ctx)?;
+    /// The person who committed the bid, who receives the rent back. Anyone may trigger the refund
+    #[account(mut)]
+    pub bidder: AccountInfo<'info>,
+    /// The bidder's token account into which the deposit is refunded
+    #[account(mut)]
+    pub bidder_account: AccountInfo<'info>,
+    /// The seller's token account into which an unrevealed bid's deposit is forfeited
+    #[account(mut)]
+    pub seller_proceeds_account: AccountInfo<'info>,
 
-            ctx.accounts.escrow_account.close(ctx.accounts.rent_payer.to_account_info())?;
-        }
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(mut, constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
 
-        Ok(())
-    }
+    // Required system-wide accounts
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(bump_seed: u8)]
-pub struct Tender<'info> {
-    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer]
-    #[account(init_if_needed,
-        payer = seller,
-        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), seller.key().as_ref()],
-        bump = bump_seed,
+pub struct SettleSealed<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
     )]
     pub escrow_account: Account<'info, EscrowAccount>,
-    /// The account in which to store the tokens. It should be the associated token account for the escrow_account's public key
-    #[account(init_if_needed,
-        payer = seller,
-        associated_token::mint = mint,
-        associated_token::authority = escrow_account,
-    )]     
-    pub escrow_token_account: Account<'info, token::TokenAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+    /// The winner's commitment, which is closed and its rent returned to the winner
+    #[account(mut,
+        close = winner,
+        seeds = [_BID_SEED, escrow_account.key().as_ref(), winner.key().as_ref()],
+        bump = bid_commitment.bump_seed,
+    )]
+    pub bid_commitment: Account<'info, BidCommitment>,
+    /// The account that holds the winner's deposit. Must be the bid_commitment's associated token account for the purchase_mint
+    #[account(mut, address=token_interface::get_associated_token_address(&bid_commitment.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub deposit_vault: AccountInfo<'info>,
 
-    /// The seller who is creating this escrow account. The seller must be the signer of this transaction
+    /// The person who paid to create the account and will receive the rent back
     #[account(mut)]
-    pub seller: Signer<'info>,
-    /// The user that will receive the tokens from this escrow account once payment is made
+    pub rent_payer: AccountInfo<'info>,
+    /// The only user allowed to bid, or OPEN_LISTING_RECEIVER if anyone may
     pub receiver: AccountInfo<'info>,
+    /// The highest bidder, who will own the winner_token_account
+    #[account(mut, constraint = winner.key() == escrow_account.highest_bidder @ EscrowError::WrongOwner)]
+    pub winner: AccountInfo<'info>,
+    /// Anyone may settle an ended auction. Pays to create the winner_token_account if it doesn't already exist
+    #[account(mut)]
+    pub signer: Signer<'info>,
 
-    /// The mint account for the token in escrow
-    pub mint: Box<Account<'info, token::Mint>>,
-    /// The mint account for the token used to purchase from this escrow
-    pub purchase_mint: Box<Account<'info, token::Mint>>,
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to bid. Must be owned by the purchase_token_program
+    #[account(mut, constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
 
-    /// The seller's token account into which the proceeds will be transferred
-    #[account(constraint=(seller_proceeds_account.mint == purchase_mint.key() && seller_proceeds_account.owner == seller.key()))]
-    pub seller_proceeds_account: Box<Account<'info, token::TokenAccount>>,
-    /// The seller's token account from which the tokens for sale will be trasnferred to create the escrow
-    #[account(mut, constraint=(sell_from_account.mint == mint.key() && sell_from_account.owner == seller.key()))]
-    pub sell_from_account: Box<Account<'info, token::TokenAccount>>,
+    /// The seller's token account into which the winning bid will be transferred
+    #[account(mut)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The treasury's token account into which the protocol fee will be transferred. Only checked when a fee is owed, so
+    /// any account may be passed while the fee is zero
+    #[account(mut)]
+    pub treasury_account: AccountInfo<'info>,
+    /// The winner's token account into which the rest of their deposit is refunded
+    #[account(mut, constraint = winner_bidder_account.key() == bid_commitment.bidder_account @ EscrowError::WrongOwner)]
+    pub winner_bidder_account: AccountInfo<'info>,
+    /// The winner's associated token account into which the asset for sale will be deposited. Created if it doesn't already exist
+    #[account(mut, address=token_interface::get_associated_token_address(&winner.key(), &mint.key(), token_program.key))]
+    pub winner_token_account: AccountInfo<'info>,
+
+    /// The global configuration holding the protocol fee. No fee is taken if it hasn't been created
+    #[account(seeds = [_CONFIG_SEED], bump)]
+    pub config: AccountInfo<'info>,
 
     // Required system-wide accounts
-    #[account(address=token::ID)]
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
     pub token_program: AccountInfo<'info>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
     #[account(address=associated_token::ID)]
     pub associated_token_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
@@ -299,125 +2879,233 @@ pub struct Tender<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(bump_seed: u8)]
-pub struct TenderFromMint<'info> {
-    /// The account in which to store the escrow metadata. This must be a PDA with seeds ["escrow", seller_proceeds_account, receiver, mint, purchase_mint, rent_payer]
-    #[account(init_if_needed,
-        payer = payer,
-        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), payer.key().as_ref()],
-        bump = bump_seed,
+pub struct Cancel<'info> {
+    /// The account that holds the escrow metadata
+    #[account(mut,
+        close=seller,
+        has_one = seller,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        constraint = escrow_account.highest_bid == 0 @ EscrowError::AuctionHasBids,
+        constraint = escrow_account.lazy_mint_authority.is_none() @ EscrowError::LazyMintEscrow,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), seller.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow_account.bump_seed,
     )]
     pub escrow_account: Account<'info, EscrowAccount>,
-    /// The account in which to store the tokens. It should be the associated token account for the escrow_account's public key
-    #[account(init_if_needed,
-        payer = payer,
-        associated_token::mint = mint,
-        associated_token::authority = escrow_account,
-    )]     
-    pub escrow_token_account: Account<'info, token::TokenAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+    /// The account that locks the bids of an English auction, which is closed. Must be the escrow_account's associated
+    /// token account for the purchase_mint, and is ignored for other escrows
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &purchase_mint.key(), purchase_token_program.key))]
+    pub bid_vault: AccountInfo<'info>,
 
-    /// The mint_authority who is creating this escrow account. Must be the signer of this transaction
-    #[account(mut)]
-    pub mint_authority: AccountInfo<'info>,
-    /// The account that will fund the creation of the escrow and token account
+    /// The seller who created the escrow account. Must be the signer.
     #[account(mut)]
-    pub payer: Signer<'info>,
-    /// The user that will receive the tokens from this escrow account once payment is made
+    pub seller: Signer<'info>,
+    /// The user that will receive the tokens from this escrow account once payment is made.
     pub receiver: AccountInfo<'info>,
 
-    /// The mint account for the token in escrow
-    #[account(mut)]
-    pub mint: Box<Account<'info, token::Mint>>,
-    /// The mint account for the token used to purchase from this escrow
-    pub purchase_mint: Box<Account<'info, token::Mint>>,
+    /// The mint account for the token in escrow, whose authority a lazy mint escrow restores. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from this escrow. Must be owned by the purchase_token_program
+    #[account(mut, constraint = *purchase_mint.owner == purchase_token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub purchase_mint: AccountInfo<'info>,
 
     /// The seller's token account into which the proceeds will be transferred
-    #[account(constraint=(seller_proceeds_account.mint == purchase_mint.key()))]
-    pub seller_proceeds_account: Box<Account<'info, token::TokenAccount>>,
+    #[account(mut, constraint = token_interface::token_account_owner(&seller_proceeds_account) == Some(seller.key()) @ EscrowError::WrongOwner)]
+    pub seller_proceeds_account: AccountInfo<'info>,
+    /// The seller's token account to which the escrowed tokens will be returned (note: does not have to be the original account that deposited)
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&sell_from_account) == Some(mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&sell_from_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub sell_from_account: AccountInfo<'info>,
 
     // Required system-wide accounts
-    #[account(address=token::ID)]
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
     pub token_program: AccountInfo<'info>,
-    #[account(address=associated_token::ID)]
-    pub associated_token_program: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+    /// The token program that owns the purchase_mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(purchase_token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub purchase_token_program: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Purchase<'info> {
-    /// The account that holds the escrow metadata
+pub struct UpdatePrice<'info> {
+    /// The account that holds the escrow metadata. Only a fixed price escrow can be repriced
     #[account(mut,
-        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref()],
-        bump = escrow_account.bump_seed,
+        has_one = seller @ EscrowError::NotSeller,
+        has_one = mint,
+        constraint = escrow_account.pricing_mode == PricingMode::Fixed @ EscrowError::NotFixedPrice,
     )]
     pub escrow_account: Account<'info, EscrowAccount>,
     /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
-    #[account(mut, address=get_associated_token_address(&escrow_account.key(), &mint.key()))]
-    pub escrow_token_account: Account<'info, token::TokenAccount>,
+    #[account(address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), mint.owner))]
+    pub escrow_token_account: AccountInfo<'info>,
 
-    /// The person who paid to create the account and will receive the rent back
+    /// The seller recorded for the escrow. Must be the signer
+    pub seller: Signer<'info>,
+    /// The mint account for the token in escrow
+    pub mint: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawPartial<'info> {
+    /// The account that holds the escrow metadata. Only a fixed price escrow can be withdrawn from, and not a minted
+    /// one, whose tokens the seller never held and which cancel_minted returns to a designated account
+    #[account(mut,
+        has_one = seller,
+        has_one = mint,
+        has_one = rent_payer,
+        constraint = escrow_account.pricing_mode == PricingMode::Fixed @ EscrowError::NotFixedPrice,
+        constraint = !escrow_account.minted @ EscrowError::MintedEscrow,
+        constraint = escrow_account.lazy_mint_authority.is_none() @ EscrowError::LazyMintEscrow,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// The seller recorded for the escrow. Must be the signer
+    pub seller: Signer<'info>,
+    /// The account that paid the rent for the escrow, refunded if the withdrawal empties it
     #[account(mut)]
     pub rent_payer: AccountInfo<'info>,
-    /// The user that will receive the tokens from this escrow account once payment is made.
-    pub receiver: AccountInfo<'info>,
-    /// The person paying to release the tokens from escrow. Must be the signer and own the buy_from_account
+
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
+    /// The seller's token account to which the withdrawn tokens will be returned
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&sell_from_account) == Some(mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&sell_from_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub sell_from_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMinted<'info> {
+    /// The account that holds the escrow metadata. Must have been created by tender_from_mint
+    #[account(mut,
+        close = rent_payer,
+        has_one = rent_payer,
+        has_one = mint,
+        constraint = escrow_account.minted @ EscrowError::NotMinted,
+        constraint = escrow_account.highest_bid == 0 @ EscrowError::AuctionHasBids,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+    /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// Either the account that paid the rent for the escrow or the mint's authority. Must be the signer
+    #[account(constraint =
+        authority.key() == escrow_account.rent_payer || token_interface::mint_authority(&mint) == Some(authority.key())
+        @ EscrowError::NotCancelAuthority
+    )]
+    pub authority: Signer<'info>,
+    /// The account that paid the rent for the escrow, which is refunded
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub rent_payer: AccountInfo<'info>,
 
-    /// The mint account for the token in escrow
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
     pub mint: AccountInfo<'info>,
-    /// The mint account for the token used to purchase from this escrow
-    pub purchase_mint: AccountInfo<'info>,
+    /// The token account to which the escrowed tokens are returned. Unused when burning them
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
 
-    /// The seller's token account into which the proceeds will be transferred
+    // Required system-wide accounts
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelLazyMint<'info> {
+    /// The account that holds the escrow metadata. Must have been created by tender_lazy_mint
+    #[account(mut,
+        close = rent_payer,
+        has_one = rent_payer,
+        has_one = mint,
+        constraint = escrow_account.lazy_mint_authority.is_some() @ EscrowError::NotLazyMint,
+    )]
+    pub escrow_account: Account<'info, EscrowAccount>,
+
+    /// Either the account that paid the rent for the escrow or the mint authority the escrow took over. Must be the signer
+    #[account(constraint =
+        authority.key() == escrow_account.rent_payer || Some(authority.key()) == escrow_account.lazy_mint_authority
+        @ EscrowError::NotCancelAuthority
+    )]
+    pub authority: Signer<'info>,
+    /// The account that paid the rent for the escrow, which is refunded
     #[account(mut)]
-    pub seller_proceeds_account: Box<Account<'info, token::TokenAccount>>,
-    /// The signer's token account which will pay the purchase price
-    #[account(mut, constraint=(buy_from_account.mint == purchase_mint.key() && buy_from_account.owner == signer.key()))]
-    pub buy_from_account: Box<Account<'info, token::TokenAccount>>,
-    /// The receiver's token account into which the asset for sale will be deposited
-    #[account(mut, constraint=(buy_to_account.mint == mint.key() && buy_to_account.owner == receiver.key()))]
-    pub buy_to_account: Box<Account<'info, token::TokenAccount>>,
+    pub rent_payer: AccountInfo<'info>,
+
+    /// The mint account whose authority is handed back. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
 
     // Required system-wide accounts
-    #[account(address=token::ID)]
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
     pub token_program: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Cancel<'info> {
+pub struct CloseExpired<'info> {
     /// The account that holds the escrow metadata
     #[account(mut,
-        close=seller,
-        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), seller.key().as_ref()],
+        close=rent_payer,
+        has_one = seller,
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
         bump = escrow_account.bump_seed,
     )]
     pub escrow_account: Account<'info, EscrowAccount>,
     /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
-    #[account(mut, address=get_associated_token_address(&escrow_account.key(), &mint.key()))]
-    pub escrow_token_account: Account<'info, token::TokenAccount>,
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
 
-    /// The seller who created the escrow account. Must be the signer.
+    /// The person who paid to create the account and will receive the rent back
     #[account(mut)]
-    pub seller: Signer<'info>,
-    /// The user that will receive the tokens from this escrow account once payment is made.
+    pub rent_payer: AccountInfo<'info>,
+    /// The seller recorded for the escrow
+    pub seller: AccountInfo<'info>,
+    /// The user that would have received the tokens from this escrow account
     pub receiver: AccountInfo<'info>,
 
-    /// The mint account for the token in escrow
-    pub mint: Box<Account<'info, token::Mint>>,
+    /// The mint account for the token in escrow, whose authority a lazy mint escrow restores. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
     /// The mint account for the token used to purchase from this escrow
-    pub purchase_mint: Box<Account<'info, token::Mint>>,
+    pub purchase_mint: AccountInfo<'info>,
 
-    /// The seller's token account into which the proceeds will be transferred
-    #[account(mut, constraint=(seller_proceeds_account.owner == seller.key()))]
-    pub seller_proceeds_account: Box<Account<'info, token::TokenAccount>>,
+    /// The seller's token account into which the proceeds would have been transferred
+    pub seller_proceeds_account: AccountInfo<'info>,
     /// The seller's token account to which the escrowed tokens will be returned (note: does not have to be the original account that deposited)
-    #[account(mut, constraint=(sell_from_account.mint == mint.key() && sell_from_account.owner == seller.key()))]
-    pub sell_from_account: Box<Account<'info, token::TokenAccount>>,
+    #[account(mut,
+        constraint = token_interface::token_account_mint(&sell_from_account) == Some(mint.key()) @ EscrowError::WrongMint,
+        constraint = token_interface::token_account_owner(&sell_from_account) == Some(seller.key()) @ EscrowError::WrongOwner,
+    )]
+    pub sell_from_account: AccountInfo<'info>,
 
     // Required system-wide accounts
-    #[account(address=token::ID)]
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
     pub token_program: AccountInfo<'info>,
 }
 
@@ -425,13 +3113,21 @@ pub struct Cancel<'info> {
 pub struct Burn<'info> {
     /// The account that holds the escrow metadata
     #[account(mut,
-        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref()],
+        has_one = seller_proceeds_account,
+        has_one = receiver,
+        has_one = mint,
+        has_one = purchase_mint,
+        has_one = rent_payer,
+        constraint = escrow_account.highest_bid == 0 @ EscrowError::AuctionHasBids,
+        constraint = escrow_account.commitment_count == 0 @ EscrowError::AuctionHasCommitments,
+        constraint = escrow_account.lazy_mint_authority.is_none() @ EscrowError::LazyMintEscrow,
+        seeds = [_ESCROW_SEED, seller_proceeds_account.key().as_ref(), receiver.key().as_ref(), mint.key().as_ref(), purchase_mint.key().as_ref(), rent_payer.key().as_ref(), escrow_account.escrow_id.to_le_bytes().as_ref()],
         bump = escrow_account.bump_seed,
     )]
     pub escrow_account: Account<'info, EscrowAccount>,
     /// The account that stores the tokens in escrow. Must be the associated account for the escrow_account
-    #[account(mut, address=get_associated_token_address(&escrow_account.key(), &mint.key()))]
-    pub escrow_token_account: Account<'info, token::TokenAccount>,
+    #[account(mut, address=token_interface::get_associated_token_address(&escrow_account.key(), &mint.key(), token_program.key))]
+    pub escrow_token_account: AccountInfo<'info>,
 
     /// The account that paid the rent to create this account. They must be the signer
     #[account(mut)]
@@ -439,24 +3135,634 @@ pub struct Burn<'info> {
     /// The user that will receive the tokens from this escrow account once payment is made.
     pub receiver: AccountInfo<'info>,
 
-    /// The mint account for the token in escrow
-    #[account(mut)]
-    pub mint: Box<Account<'info, token::Mint>>,
+    /// The mint account for the token in escrow. Must be owned by the token_program
+    #[account(mut, constraint = *mint.owner == token_program.key() @ EscrowError::InvalidTokenProgram)]
+    pub mint: AccountInfo<'info>,
     /// The mint account for the token used to purchase from this escrow
-    pub purchase_mint: Box<Account<'info, token::Mint>>,
+    pub purchase_mint: AccountInfo<'info>,
 
     /// The seller's token account into which the proceeds will be transferred
     #[account(mut)]
-    pub seller_proceeds_account: Box<Account<'info, token::TokenAccount>>,
+    pub seller_proceeds_account: AccountInfo<'info>,
 
     // Required system-wide accounts
-    #[account(address=token::ID)]
+    /// The token program that owns the mint, either the original token program or Token-2022
+    #[account(constraint = token_interface::is_token_program(token_program.key) @ EscrowError::InvalidTokenProgram)]
     pub token_program: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    /// The global configuration account. This must be a PDA with seeds ["config"], and can only be created once
+    #[account(init,
+        payer = admin,
+        seeds = [_CONFIG_SEED],
+        bump,
+    )]
+    pub config: Account<'info, ConfigAccount>,
+    /// The admin who may later update the configuration. Must be the program's upgrade authority and the signer of
+    /// this transaction, so nobody can claim the configuration before the deployer does
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// This program
+    #[account(constraint = program.programdata_address() == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Escrow>,
+    /// This program's program data account, which records its upgrade authority
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ EscrowError::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+
+    // Required system-wide accounts
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    /// The global configuration account
+    #[account(mut,
+        has_one = admin @ EscrowError::NotAdmin,
+        seeds = [_CONFIG_SEED],
+        bump = config.bump_seed,
+    )]
+    pub config: Account<'info, ConfigAccount>,
+    /// The admin recorded in the configuration. Must be the signer of this transaction
+    pub admin: Signer<'info>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct ConfigAccount {
+    /// The account allowed to update the configuration
+    pub admin: Pubkey,
+    /// The protocol fee taken from every purchase, in basis points of the purchase cost
+    pub fee_basis_points: u16,
+    /// The owner of the token accounts that receive the protocol fee
+    pub treasury: Pubkey,
+    pub bump_seed: u8,
+}
+
 #[account]
 #[derive(Default)]
 pub struct EscrowAccount {
     pub total_purchase_cost: u64,
     pub bump_seed: u8,
+    /// The seller who owns the proceeds of this escrow
+    pub seller: Pubkey,
+    /// The user that will receive the tokens from this escrow account once payment is made
+    pub receiver: Pubkey,
+    /// The mint account for the token in escrow
+    pub mint: Pubkey,
+    /// The mint account for the token used to purchase from this escrow
+    pub purchase_mint: Pubkey,
+    /// The seller's token account into which the proceeds will be transferred
+    pub seller_proceeds_account: Pubkey,
+    /// The account that paid the rent to create this escrow and will receive it back
+    pub rent_payer: Pubkey,
+    /// The slot in which the escrow was created
+    pub created_at_slot: u64,
+    /// The unix timestamp at which the escrow was created
+    pub created_at_timestamp: i64,
+    /// The total quantity tendered into this escrow
+    pub original_quantity: u64,
+    /// The total quantity purchased from this escrow
+    pub quantity_sold: u64,
+    /// The unix timestamp after which the escrow can no longer be purchased, if any
+    pub expires_at: Option<i64>,
+    /// Who bears the purchase mint's transfer fee, if it has one
+    pub fee_bearer: FeeBearer,
+    /// How the cost of a purchase is determined
+    pub pricing_mode: PricingMode,
+    /// How a fixed price purchase of part of a lot is priced
+    pub rounding: RoundingPolicy,
+    /// The highest bid locked in the bid vault of an English auction, or zero if there are no bids
+    pub highest_bid: u64,
+    /// The wallet that placed the highest bid and will receive the tokens
+    pub highest_bidder: Pubkey,
+    /// The highest bidder's associated account, which is refunded if an English auction bid is outbid. For a sealed-bid
+    /// auction, the account the winning deposit came from
+    pub highest_bidder_account: Pubkey,
+    /// The price of each lot of a fixed price escrow
+    pub price_per_lot: u64,
+    /// The quantity in each lot of a fixed price escrow. Tenders must be whole lots, as must purchases unless the
+    /// rounding policy prices part lots
+    pub lot_size: u64,
+    /// Whether the tokens were minted into the escrow by tender_from_mint rather than deposited by the seller
+    pub minted: bool,
+    /// Distinguishes independent escrows with the same accounts and mints. The default listing uses zero
+    pub escrow_id: u64,
+    /// For a lazy mint escrow, which holds the mint authority and mints each purchase, the mint authority to restore
+    /// when it closes
+    pub lazy_mint_authority: Option<Pubkey>,
+    /// The number of sealed bid deposits committed to this escrow that haven't yet been refunded or forfeited
+    pub commitment_count: u64,
+}
+
+impl EscrowAccount {
+    pub const LEN: usize = 8 + 1 + 6 * 32 + 8 + 8 + 8 + 8 + (1 + 8) + 1 + PricingMode::LEN + RoundingPolicy::LEN + 8 + 2 * 32 + 8 + 8 + 1 + 8 + (1 + 32) + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PricingMode {
+    /// Purchases pay price_per_lot for each lot, rounded by the escrow's RoundingPolicy for part lots
+    Fixed,
+    /// Purchases pay a unit price that falls linearly from start_price at start_time to end_price at end_time
+    DutchAuction {
+        start_price: u64,
+        end_price: u64,
+        start_time: i64,
+        end_time: i64,
+    },
+    /// The whole escrow is sold to the highest bid of at least min_bid once end_time passes
+    EnglishAuction {
+        min_bid: u64,
+        end_time: i64,
+    },
+    /// Bids are committed as hashes until commit_end_time and revealed until reveal_end_time, after which the whole
+    /// escrow is sold to the highest revealed bid of at least min_bid
+    SealedBidAuction {
+        min_bid: u64,
+        commit_end_time: i64,
+        reveal_end_time: i64,
+    },
+}
+
+impl PricingMode {
+    /// The size of the largest variant
+    pub const LEN: usize = 1 + 8 + 8 + 8 + 8;
+}
+
+impl Default for PricingMode {
+    fn default() -> Self {
+        PricingMode::Fixed
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingPolicy {
+    /// Only whole lots can be purchased
+    Exact,
+    /// Part lots are priced at their share of price_per_lot, rounded up in the seller's favour
+    RoundUp,
+    /// Part lots are priced at their share of price_per_lot, rounded down in the buyer's favour, but purchases costing
+    /// less than min_cost are rejected
+    RoundDown {
+        min_cost: u64,
+    },
+}
+
+impl RoundingPolicy {
+    /// The size of the largest variant
+    pub const LEN: usize = 1 + 8;
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        RoundingPolicy::Exact
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct BidCommitment {
+    /// The escrow this bid is for
+    pub escrow: Pubkey,
+    /// The person who committed the bid
+    pub bidder: Pubkey,
+    /// The bidder's token account the deposit came from and is refunded to
+    pub bidder_account: Pubkey,
+    /// The seller's token account the deposit is forfeited to if the bid is never revealed
+    pub seller_proceeds_account: Pubkey,
+    /// sha256(escrow, bidder, bid as little-endian u64, salt)
+    pub commitment: [u8; 32],
+    /// The amount locked in the deposit vault, which the revealed bid must not exceed
+    pub deposit: u64,
+    /// The revealed bid, or zero until it is revealed
+    pub revealed_bid: u64,
+    /// The end of the escrow's reveal window, after which a losing deposit can be refunded
+    pub reveal_end_time: i64,
+    pub bump_seed: u8,
+}
+
+/// Who bears the transfer fee charged by a Token-2022 purchase mint. The escrowed tokens are always priced on the
+/// quantity that actually arrived in escrow, and any fee on delivering them is withheld from what the recipient receives
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeeBearer {
+    /// The buyer sends enough extra that the seller and treasury receive the full amounts
+    Buyer,
+    /// The buyer sends the purchase cost and the seller receives it less the fee
+    Seller,
+}
+
+impl Default for FeeBearer {
+    fn default() -> Self {
+        FeeBearer::Seller
+    }
+}
+
+/// Emitted when a seller tenders tokens from their own account into an escrow
+#[event]
+pub struct EscrowTendered {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity added to the escrow by this tender
+    pub quantity: u64,
+    /// The price of each lot
+    pub price_per_lot: u64,
+    /// The quantity in each lot
+    pub lot_size: u64,
+    /// The purchase cost added to the escrow by this tender
+    pub cost: u64,
+    /// The total purchase cost of the escrow after this tender
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when a seller tenders tokens into a Dutch auction
+#[event]
+pub struct EscrowDutchAuctionTendered {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity added to the auction by this tender
+    pub quantity: u64,
+    /// The unit price when the auction starts
+    pub start_price: u64,
+    /// The unit price once the auction ends
+    pub end_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+/// Emitted when a seller tenders tokens into an English auction
+#[event]
+pub struct EscrowEnglishAuctionTendered {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity added to the auction by this tender
+    pub quantity: u64,
+    /// The lowest bid the auction will accept
+    pub min_bid: u64,
+    pub end_time: i64,
+}
+
+/// Emitted when a seller tenders tokens into a sealed-bid auction
+#[event]
+pub struct EscrowSealedBidAuctionTendered {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity added to the auction by this tender
+    pub quantity: u64,
+    /// The lowest bid the auction will accept
+    pub min_bid: u64,
+    pub commit_end_time: i64,
+    pub reveal_end_time: i64,
+}
+
+/// Emitted when tokens are minted directly into an escrow
+#[event]
+pub struct EscrowMinted {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity minted into the escrow
+    pub quantity: u64,
+    /// The price of each lot
+    pub price_per_lot: u64,
+    /// The quantity in each lot
+    pub lot_size: u64,
+    /// The purchase cost added to the escrow
+    pub cost: u64,
+    /// The total purchase cost of the escrow after this mint
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when a lazy mint escrow is tendered
+#[event]
+pub struct EscrowLazyMintTendered {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The most the escrow will ever mint
+    pub supply_cap: u64,
+    /// The price of each lot
+    pub price_per_lot: u64,
+    /// The quantity in each lot
+    pub lot_size: u64,
+    /// The cost of purchasing the whole supply cap
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when some or all of an escrow is purchased
+#[event]
+pub struct EscrowPurchased {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The signer who paid for the purchase
+    pub buyer: Pubkey,
+    /// The owner of the token account the purchase was delivered to
+    pub recipient: Pubkey,
+    /// The quantity delivered to the recipient
+    pub quantity: u64,
+    /// The amount paid by the buyer
+    pub cost: u64,
+    /// The portion of the cost paid to the treasury rather than the seller
+    pub protocol_fee: u64,
+    /// The amount that actually arrived in the seller's proceeds account, after any transfer fee
+    pub seller_received: u64,
+    /// The purchase cost remaining in the escrow
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when a new highest bid is placed on an English auction
+#[event]
+pub struct EscrowBid {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    pub bidder: Pubkey,
+    /// The amount locked by this bid
+    pub bid: u64,
+    /// The bid that was outbid and refunded, or zero if this is the first
+    pub previous_bid: u64,
+}
+
+/// Emitted when a sealed bid is committed
+#[event]
+pub struct EscrowBidCommitted {
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    /// The amount locked, which is the most the bid can be
+    pub deposit: u64,
+}
+
+/// Emitted when a sealed bid is revealed
+#[event]
+pub struct EscrowBidRevealed {
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    pub bid: u64,
+    /// The highest bid revealed so far, including this one
+    pub highest_bid: u64,
+}
+
+/// Emitted when a losing sealed bid's deposit is refunded
+#[event]
+pub struct EscrowBidRefunded {
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    pub refund: u64,
+}
+
+/// Emitted when an unrevealed sealed bid's deposit is forfeited to the seller
+#[event]
+pub struct EscrowBidForfeited {
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    pub deposit: u64,
+}
+
+/// Emitted when an ended auction is settled to its winner
+#[event]
+pub struct EscrowSettled {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    pub winner: Pubkey,
+    /// The quantity delivered to the winner
+    pub quantity: u64,
+    /// The winning bid
+    pub winning_bid: u64,
+    /// The portion of the winning bid paid to the treasury rather than the seller
+    pub protocol_fee: u64,
+}
+
+/// Emitted when the seller cancels an escrow and the tokens are returned
+#[event]
+pub struct EscrowCancelled {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity returned to the seller
+    pub quantity: u64,
+    /// The purchase cost that was outstanding when the escrow was cancelled
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when the seller reprices a fixed price escrow
+#[event]
+pub struct EscrowRepriced {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The price of each lot before the update
+    pub old_price_per_lot: u64,
+    /// The price of each lot after the update
+    pub price_per_lot: u64,
+    /// The purchase cost of the remaining lots before the update
+    pub old_total_purchase_cost: u64,
+    /// The purchase cost of the remaining lots after the update
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when the seller withdraws part of a fixed price escrow
+#[event]
+pub struct EscrowWithdrawn {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity returned to the seller
+    pub quantity: u64,
+    /// The purchase cost removed from the escrow
+    pub cost: u64,
+    /// The total purchase cost of the escrow after the withdrawal
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when an expired escrow is closed and the tokens are returned to the seller
+#[event]
+pub struct EscrowExpired {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity returned to the seller
+    pub quantity: u64,
+    /// The purchase cost that was outstanding when the escrow expired
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when tokens held in an escrow are burned
+#[event]
+pub struct EscrowBurned {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+    /// The quantity burned
+    pub quantity: u64,
+    /// The purchase cost remaining in the escrow
+    pub total_purchase_cost: u64,
+}
+
+/// Emitted when an escrow and its token account are closed
+#[event]
+pub struct EscrowClosed {
+    pub escrow: Pubkey,
+    pub seller_proceeds_account: Pubkey,
+    pub receiver: Pubkey,
+    pub mint: Pubkey,
+    pub purchase_mint: Pubkey,
+    pub rent_payer: Pubkey,
+    pub escrow_id: u64,
+}
+
+#[error]
+pub enum EscrowError {
+    #[msg("Quantity must be greater than zero")]
+    ZeroQuantity,
+    #[msg("Purchase cost must be greater than zero")]
+    ZeroCost,
+    #[msg("Tendered price and lot size do not match the existing escrow")]
+    PriceRatioMismatch,
+    #[msg("Quantity must be a whole number of lots")]
+    NonDivisiblePurchase,
+    #[msg("Quantity exceeds the amount held in escrow")]
+    QuantityExceedsEscrow,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Token account has the wrong mint")]
+    WrongMint,
+    #[msg("Token account has the wrong owner")]
+    WrongOwner,
+    #[msg("Tender terms do not match the existing escrow")]
+    TermsMismatch,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("The escrow has expired")]
+    EscrowExpired,
+    #[msg("The escrow has not expired")]
+    EscrowNotExpired,
+    #[msg("Fee cannot exceed 10000 basis points")]
+    InvalidFee,
+    #[msg("Account is not owned by a supported token program")]
+    InvalidTokenProgram,
+    #[msg("Auction start price must not be below its end price")]
+    InvalidAuctionPrices,
+    #[msg("Auction must end after it starts")]
+    InvalidAuctionTimes,
+    #[msg("Auctions can't be purchased directly")]
+    NotPurchasable,
+    #[msg("The escrow is not this kind of auction")]
+    NotAnAuction,
+    #[msg("The auction has ended")]
+    AuctionEnded,
+    #[msg("The auction has not ended")]
+    AuctionNotEnded,
+    #[msg("Bid must be at least the minimum and higher than the current bid")]
+    BidTooLow,
+    #[msg("The auction has no bids")]
+    NoBids,
+    #[msg("The auction has bids locked in it")]
+    AuctionHasBids,
+    #[msg("Bids can only be committed before the commit window ends")]
+    NotInCommitPhase,
+    #[msg("Bids can only be revealed during the reveal window")]
+    NotInRevealPhase,
+    #[msg("The bid has already been revealed")]
+    AlreadyRevealed,
+    #[msg("The bid and salt do not match the commitment")]
+    InvalidReveal,
+    #[msg("The bid exceeds its deposit")]
+    BidExceedsDeposit,
+    #[msg("The winning deposit is only released by settling the auction")]
+    WinningBidNotRefundable,
+    #[msg("Purchase cost exceeds the buyer's maximum")]
+    MaxCostExceeded,
+    #[msg("The budget doesn't cover the smallest purchasable quantity")]
+    InsufficientBudget,
+    #[msg("Purchase cost rounds below the escrow's minimum")]
+    PurchaseBelowMinimum,
+    #[msg("The escrow doesn't have a fixed price")]
+    NotFixedPrice,
+    #[msg("The escrow wasn't created from a mint")]
+    NotMinted,
+    #[msg("Only the rent payer or the mint authority can cancel a minted escrow")]
+    NotCancelAuthority,
+    #[msg("Not enough of the multisig's signers signed")]
+    MultisigThresholdNotMet,
+    #[msg("The account isn't the mint's authority")]
+    WrongMintAuthority,
+    #[msg("Not supported for a lazy mint escrow")]
+    LazyMintEscrow,
+    #[msg("The mint has no mint authority")]
+    NoMintAuthority,
+    #[msg("Only the program's upgrade authority can create the configuration")]
+    NotUpgradeAuthority,
+    #[msg("Only the config's admin can update it")]
+    NotAdmin,
+    #[msg("Only the escrow's seller can do this")]
+    NotSeller,
+    #[msg("A sealed-bid auction can't be closed while bid deposits are locked in it")]
+    AuctionHasCommitments,
+    #[msg("The escrow isn't a lazy mint escrow")]
+    NotLazyMint,
+    #[msg("Not supported for an escrow created from a mint")]
+    MintedEscrow,
 }
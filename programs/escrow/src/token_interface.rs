@@ -0,0 +1,238 @@
+//! Helpers for working with mints and token accounts owned by either the original token program or Token-2022.
+//!
+//! Anchor's `token` module only understands the original program, so accounts that may belong to either are taken
+//! as plain `AccountInfo`s and read and invoked through these functions instead.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::associated_token;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig};
+use spl_token_2022::state::{Account as TokenAccountState, Mint as MintState, Multisig};
+
+use crate::EscrowError;
+
+pub fn is_token_program(key: &Pubkey) -> bool {
+    *key == spl_token::ID || *key == spl_token_2022::ID
+}
+
+/// Whether the mint is the wrapped SOL mint of either token program
+pub fn is_native_mint(key: &Pubkey) -> bool {
+    *key == spl_token::native_mint::ID || *key == spl_token_2022::native_mint::ID
+}
+
+/// The associated token account address, which includes the owning token program in its seeds
+pub fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &associated_token::ID,
+    ).0
+}
+
+pub fn unpack_token_account(info: &AccountInfo) -> Result<TokenAccountState, ProgramError> {
+    if !is_token_program(info.owner) {
+        return Err(EscrowError::InvalidTokenProgram.into());
+    }
+    let data = info.try_borrow_data()?;
+    Ok(StateWithExtensions::<TokenAccountState>::unpack(&data)?.base)
+}
+
+pub fn unpack_mint(info: &AccountInfo) -> Result<MintState, ProgramError> {
+    if !is_token_program(info.owner) {
+        return Err(EscrowError::InvalidTokenProgram.into());
+    }
+    let data = info.try_borrow_data()?;
+    Ok(StateWithExtensions::<MintState>::unpack(&data)?.base)
+}
+
+/// The multisig an authority account holds, or None if it is a plain key
+pub fn unpack_multisig(info: &AccountInfo) -> Result<Option<Multisig>, ProgramError> {
+    if !is_token_program(info.owner) || info.data_len() != Multisig::LEN {
+        return Ok(None);
+    }
+    let data = info.try_borrow_data()?;
+    Ok(Some(Multisig::unpack(&data)?))
+}
+
+/// The transfer fee the mint charges in the given epoch, or None if it doesn't have the transfer fee extension
+pub fn get_transfer_fee(mint: &AccountInfo, epoch: u64) -> Result<Option<TransferFee>, ProgramError> {
+    if !is_token_program(mint.owner) {
+        return Err(EscrowError::InvalidTokenProgram.into());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    Ok(state.get_extension::<TransferFeeConfig>().ok().map(|config| *config.get_epoch_fee(epoch)))
+}
+
+/// The mint of a token account, or None if it isn't one. Intended for account constraints
+pub fn token_account_mint(info: &AccountInfo) -> Option<Pubkey> {
+    unpack_token_account(info).ok().map(|account| account.mint)
+}
+
+/// The owner of a token account, or None if it isn't one. Intended for account constraints
+pub fn token_account_owner(info: &AccountInfo) -> Option<Pubkey> {
+    unpack_token_account(info).ok().map(|account| account.owner)
+}
+
+/// The mint authority of a mint, or None if it has none or isn't a mint. Intended for account constraints
+pub fn mint_authority(info: &AccountInfo) -> Option<Pubkey> {
+    unpack_mint(info).ok().and_then(|mint| mint.mint_authority.into())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_associated_token_account_if_needed<'info>(
+    payer: AccountInfo<'info>,
+    associated_account: AccountInfo<'info>,
+    wallet: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+) -> ProgramResult {
+    if !associated_account.data_is_empty() {
+        return Ok(());
+    }
+
+    // Built by hand, as the associated token account crate's helper only targets the original token program
+    let ix = Instruction {
+        program_id: associated_token::ID,
+        accounts: vec![
+            AccountMeta::new(*payer.key, true),
+            AccountMeta::new(*associated_account.key, false),
+            AccountMeta::new_readonly(*wallet.key, false),
+            AccountMeta::new_readonly(*mint.key, false),
+            AccountMeta::new_readonly(*system_program.key, false),
+            AccountMeta::new_readonly(*token_program.key, false),
+            AccountMeta::new_readonly(*rent.key, false),
+        ],
+        data: vec![],
+    };
+    solana_program::program::invoke(
+        &ix,
+        &[payer, associated_account, wallet, mint, system_program, token_program, rent, associated_token_program],
+    )
+}
+
+pub fn transfer_checked<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let decimals = unpack_mint(&mint)?.decimals;
+    let ix = spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        from.key,
+        mint.key,
+        to.key,
+        authority.key,
+        &[],
+        amount,
+        decimals,
+    )?;
+    solana_program::program::invoke_signed(&ix, &[from, mint, to, authority, token_program], signer_seeds)
+}
+
+pub fn mint_to<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    multisig_signers: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let signer_keys: Vec<&Pubkey> = multisig_signers.iter().map(|signer| signer.key).collect();
+    let ix = spl_token_2022::instruction::mint_to(
+        token_program.key,
+        mint.key,
+        to.key,
+        authority.key,
+        signer_keys.as_slice(),
+        amount,
+    )?;
+    let mut account_infos = vec![to, mint, authority, token_program];
+    account_infos.extend_from_slice(multisig_signers);
+    solana_program::program::invoke_signed(&ix, account_infos.as_slice(), signer_seeds)
+}
+
+/// Set or revoke the mint's MintTokens authority
+pub fn set_mint_authority<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    new_authority: Option<Pubkey>,
+    authority: AccountInfo<'info>,
+    multisig_signers: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let signer_keys: Vec<&Pubkey> = multisig_signers.iter().map(|signer| signer.key).collect();
+    let ix = spl_token_2022::instruction::set_authority(
+        token_program.key,
+        mint.key,
+        new_authority.as_ref(),
+        spl_token_2022::instruction::AuthorityType::MintTokens,
+        authority.key,
+        signer_keys.as_slice(),
+    )?;
+    let mut account_infos = vec![mint, authority, token_program];
+    account_infos.extend_from_slice(multisig_signers);
+    solana_program::program::invoke_signed(&ix, account_infos.as_slice(), signer_seeds)
+}
+
+pub fn burn<'info>(
+    token_program: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = spl_token_2022::instruction::burn(token_program.key, account.key, mint.key, authority.key, &[], amount)?;
+    solana_program::program::invoke_signed(&ix, &[account, mint, authority, token_program], signer_seeds)
+}
+
+/// Move any transfer fees withheld in a Token-2022 account to its mint. Anyone may harvest, so no authority is needed
+pub fn harvest_withheld_tokens_to_mint<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+) -> ProgramResult {
+    if *token_program.key != spl_token_2022::ID {
+        return Ok(());
+    }
+    let withheld = {
+        let data = account.try_borrow_data()?;
+        let state = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+        state.get_extension::<TransferFeeAmount>().map(|amount| u64::from(amount.withheld_amount)).unwrap_or(0)
+    };
+    if withheld == 0 {
+        return Ok(());
+    }
+    let ix = spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint(token_program.key, mint.key, &[account.key])?;
+    solana_program::program::invoke(&ix, &[mint, account, token_program])
+}
+
+/// Close a token account, first harvesting any withheld transfer fees, which would otherwise block closing it
+pub fn close_account<'info>(
+    token_program: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    harvest_withheld_tokens_to_mint(token_program.clone(), mint, account.clone())?;
+    let ix = spl_token_2022::instruction::close_account(token_program.key, account.key, destination.key, authority.key, &[])?;
+    solana_program::program::invoke_signed(&ix, &[account, destination, authority, token_program], signer_seeds)
+}
+
+pub fn sync_native<'info>(token_program: AccountInfo<'info>, account: AccountInfo<'info>) -> ProgramResult {
+    let ix = spl_token_2022::instruction::sync_native(token_program.key, account.key)?;
+    solana_program::program::invoke(&ix, &[account, token_program])
+}
@@ -0,0 +1,80 @@
+//! Example of listing newly minted tokens in the escrow program from another program. The mint's authority is a PDA
+//! of this program, which signs for it through the escrow program's `tender_from_mint_signed`.
+
+use anchor_lang::prelude::*;
+
+declare_id!("GwENzfozyeDei3YU9YnB55FTg2AZLtkLp6eZesj9VdKf");
+
+const _MINT_AUTHORITY_SEED: &[u8] = "mint_authority".as_bytes();
+
+#[program]
+pub mod mint_caller {
+    use super::*;
+
+    /// Mint asset_quantity_for_sale into a fixed price escrow, signing as this program's mint authority PDA
+    pub fn tender(ctx: Context<Tender>, escrow_id: u64, price_per_lot: u64, lot_size: u64, asset_quantity_for_sale: u64) -> ProgramResult {
+        let bump = *ctx.bumps.get("mint_authority").unwrap();
+        let seeds: &[&[&[u8]]] = &[&[_MINT_AUTHORITY_SEED, &[bump]]];
+        escrow::cpi::tender_from_mint_signed(
+            CpiContext::new_with_signer(
+                ctx.accounts.escrow_program.to_account_info(),
+                escrow::cpi::accounts::TenderFromMintSigned {
+                    escrow_account: ctx.accounts.escrow_account.clone(),
+                    escrow_token_account: ctx.accounts.escrow_token_account.clone(),
+                    mint_authority: ctx.accounts.mint_authority.clone(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    receiver: ctx.accounts.receiver.clone(),
+                    mint: ctx.accounts.mint.clone(),
+                    purchase_mint: ctx.accounts.purchase_mint.clone(),
+                    seller_proceeds_account: ctx.accounts.seller_proceeds_account.clone(),
+                    token_program: ctx.accounts.token_program.clone(),
+                    associated_token_program: ctx.accounts.associated_token_program.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                seeds,
+            ),
+            escrow_id,
+            price_per_lot,
+            lot_size,
+            asset_quantity_for_sale,
+            None,
+            escrow::FeeBearer::Seller,
+            escrow::RoundingPolicy::Exact,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct Tender<'info> {
+    /// The escrow to create or add to. Checked by the escrow program
+    #[account(mut)]
+    pub escrow_account: AccountInfo<'info>,
+    /// The escrow's token account. Checked by the escrow program
+    #[account(mut)]
+    pub escrow_token_account: AccountInfo<'info>,
+
+    /// This program's PDA with seeds ["mint_authority"], which must be the mint's authority
+    #[account(seeds = [_MINT_AUTHORITY_SEED], bump)]
+    pub mint_authority: AccountInfo<'info>,
+    /// The account that will fund the creation of the escrow and token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The user that will receive the tokens from the escrow once payment is made
+    pub receiver: AccountInfo<'info>,
+
+    /// The mint account for the token in escrow
+    #[account(mut)]
+    pub mint: AccountInfo<'info>,
+    /// The mint account for the token used to purchase from the escrow
+    pub purchase_mint: AccountInfo<'info>,
+    /// The seller's token account into which the proceeds will be transferred
+    pub seller_proceeds_account: AccountInfo<'info>,
+
+    // Required system-wide accounts
+    pub escrow_program: Program<'info, escrow::program::Escrow>,
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
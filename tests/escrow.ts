@@ -1,8 +1,16 @@
 import { strict as assert } from 'assert';
 import * as anchor from '@project-serum/anchor';
 import * as splToken from '@solana/spl-token';
+import { createHash, randomBytes } from 'crypto';
 
 let program = anchor.workspace.Escrow;
+let configAccount: anchor.web3.PublicKey;
+// Whether the config exists. `anchor test` loads the program without an upgrade authority, so nobody can create it and
+// purchases take no fee
+let hasConfig = false;
+
+const TOKEN_2022_PROGRAM_ID = new anchor.web3.PublicKey("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
 
 const logInfo = false ? console.log : Function.prototype;
 
@@ -14,6 +22,18 @@ const logAccounts = (description: string, accounts: Object) => {
   });
 }
 
+const assertProgramError = async (fn: () => Promise<any>, errorName: string) => {
+  const expected = program.idl.errors.find((e) => e.name === errorName);
+  try {
+    await fn();
+  } catch (e) {
+    assert.ok(e instanceof anchor.ProgramError);
+    assert.equal(e.code, expected.code);
+    return;
+  }
+  assert.fail(`Expected ${errorName}`);
+}
+
 type BasicAccounts = {
   seller: anchor.web3.Keypair,
   buyer: anchor.web3.Keypair,
@@ -23,9 +43,12 @@ type BasicAccounts = {
   sellerProceedsAccount: splToken.AccountInfo,
   buyFromAccount: splToken.AccountInfo,
   buyToAccount: splToken.AccountInfo,
+  treasuryAccount: splToken.AccountInfo,
   escrowAccount: anchor.web3.PublicKey,
   escrowTokenAccount: anchor.web3.PublicKey,
   bumpSeed: number,
+  receiver: anchor.web3.PublicKey,
+  escrowId: number,
 }
 
 type MainBalances = {
@@ -35,7 +58,43 @@ type MainBalances = {
   buyerPurchaseToken: splToken.u64,
 }
 
-const getBasicAccounts = async (provider: anchor.Provider, payer?: anchor.web3.PublicKey) => {
+// @solana/spl-token 0.1 predates Token-2022 extensions, so a transfer fee mint is built by hand: the mint padded to an
+// account's length, its account type, then the TransferFeeConfig extension with no authorities
+const createTransferFeeMint = async (provider: anchor.Provider, transferFeeBasisPoints: number, maximumFee: number = 1000000) => {
+  const mint = anchor.web3.Keypair.generate();
+  const space = 165 + 1 + 4 + 108;
+  const initTransferFeeData = Buffer.alloc(14);
+  initTransferFeeData.writeUInt8(26, 0);
+  initTransferFeeData.writeUInt8(0, 1);
+  initTransferFeeData.writeUInt16LE(transferFeeBasisPoints, 4);
+  new anchor.BN(maximumFee).toArrayLike(Buffer, 'le', 8).copy(initTransferFeeData, 6);
+
+  const tx = new anchor.web3.Transaction().add(
+    anchor.web3.SystemProgram.createAccount({
+      fromPubkey: provider.wallet.publicKey,
+      newAccountPubkey: mint.publicKey,
+      space,
+      lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+      programId: TOKEN_2022_PROGRAM_ID,
+    }),
+    new anchor.web3.TransactionInstruction({
+      programId: TOKEN_2022_PROGRAM_ID,
+      keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+      data: initTransferFeeData,
+    }),
+    splToken.Token.createInitMintInstruction(TOKEN_2022_PROGRAM_ID, mint.publicKey, 0, provider.wallet.publicKey, null),
+  );
+  await provider.send(tx, [mint]);
+  return new splToken.Token(provider.connection, mint.publicKey, TOKEN_2022_PROGRAM_ID, provider.wallet.payer);
+}
+
+// Read a token account's amount straight from its data, since spl-token 0.1 rejects accounts with extensions
+const getTokenBalance = async (connection: anchor.web3.Connection, address: anchor.web3.PublicKey) => {
+  const info = await connection.getAccountInfo(address);
+  return new anchor.BN(info.data.slice(64, 72), 'le');
+}
+
+const getBasicAccounts = async (provider: anchor.Provider, payer?: anchor.web3.PublicKey, receiver?: anchor.web3.PublicKey, nativePurchase: boolean = false, tokenProgramId: anchor.web3.PublicKey = splToken.TOKEN_PROGRAM_ID, escrowId: number = 0, transferFeeBasisPoints: number = 0) => {
   const connection = provider.connection;
 
   // wallets
@@ -47,35 +106,50 @@ const getBasicAccounts = async (provider: anchor.Provider, payer?: anchor.web3.P
 
   // mints
   const tokenDecimals = 0;
-  const mint = await splToken.Token.createMint(connection, provider.wallet.payer, provider.wallet.publicKey, null, tokenDecimals, splToken.TOKEN_PROGRAM_ID);
-  const purchaseMint = await splToken.Token.createMint(connection, provider.wallet.payer, provider.wallet.publicKey, null, tokenDecimals, splToken.TOKEN_PROGRAM_ID);
-
-  // token accounts
-  const sellFromAccount = await mint.getOrCreateAssociatedAccountInfo(seller.publicKey);
-  const buyToAccount = await mint.getOrCreateAssociatedAccountInfo(buyer.publicKey);
-  const sellerProceedsAccount = await purchaseMint.getOrCreateAssociatedAccountInfo(seller.publicKey);
-  const buyFromAccount = await purchaseMint.getOrCreateAssociatedAccountInfo(buyer.publicKey);
+  const createMint = () => transferFeeBasisPoints > 0
+    ? createTransferFeeMint(provider, transferFeeBasisPoints)
+    : splToken.Token.createMint(connection, provider.wallet.payer, provider.wallet.publicKey, null, tokenDecimals, tokenProgramId);
+  const mint = await createMint();
+  const purchaseMint = nativePurchase
+    ? new splToken.Token(connection, splToken.NATIVE_MINT, splToken.TOKEN_PROGRAM_ID, provider.wallet.payer)
+    : await createMint();
+
+  // token accounts. Accounts of a transfer fee mint carry an extension spl-token 0.1 can't read back, so they are only created
+  const getTokenAccount = async (token: splToken.Token, owner: anchor.web3.PublicKey) => transferFeeBasisPoints > 0
+    ? { address: await token.createAssociatedTokenAccount(owner) } as splToken.AccountInfo
+    : await token.getOrCreateAssociatedAccountInfo(owner);
+  const sellFromAccount = await getTokenAccount(mint, seller.publicKey);
+  const buyToAccount = await getTokenAccount(mint, buyer.publicKey);
+  const sellerProceedsAccount = await getTokenAccount(purchaseMint, seller.publicKey);
+  const buyFromAccount = await getTokenAccount(purchaseMint, buyer.publicKey);
+  const treasuryAccount = await getTokenAccount(purchaseMint, provider.wallet.publicKey);
 
   // mints
   await mint.mintTo(sellFromAccount.address, provider.wallet.publicKey, [], 100);
-  await purchaseMint.mintTo(buyFromAccount.address, provider.wallet.publicKey, [], 200);
+  if (!nativePurchase) {
+    await purchaseMint.mintTo(buyFromAccount.address, provider.wallet.publicKey, [], 200);
+  }
 
   // program-related accounts
   if (!payer) {
     payer = seller.publicKey;
   }
+  if (!receiver) {
+    receiver = buyer.publicKey;
+  }
   const [ escrowAccount, bumpSeed ] = await anchor.web3.PublicKey.findProgramAddress(
     [
       Buffer.from("escrow"),
       sellerProceedsAccount.address.toBuffer(),
-      buyer.publicKey.toBuffer(),
+      receiver.toBuffer(),
       mint.publicKey.toBuffer(),
       purchaseMint.publicKey.toBuffer(),
       payer.toBuffer(),
+      new anchor.BN(escrowId).toArrayLike(Buffer, 'le', 8),
     ],
     program.programId,
   );
-  const escrowTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, splToken.TOKEN_PROGRAM_ID, mint.publicKey, escrowAccount, true);
+  const escrowTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, tokenProgramId, mint.publicKey, escrowAccount, true);
 
   return {
     seller: seller,
@@ -86,9 +160,12 @@ const getBasicAccounts = async (provider: anchor.Provider, payer?: anchor.web3.P
     sellerProceedsAccount: sellerProceedsAccount,
     buyFromAccount: buyFromAccount,
     buyToAccount: buyToAccount,
+    treasuryAccount: treasuryAccount,
     escrowAccount: escrowAccount,
     escrowTokenAccount: escrowTokenAccount,
     bumpSeed: bumpSeed,
+    receiver: receiver,
+    escrowId: escrowId,
   };
 }
 
@@ -109,18 +186,19 @@ const logMainBalances = (header: string, balances: MainBalances) => {
   logInfo('Buyer purchase balance:', balances.buyerPurchaseToken.toNumber());
 }
 
-const doDefaultInit = async (basicAccounts: BasicAccounts, totalPurchaseCost: number, assetQty: number) => {
-    // Create the new account and tender it with the program.
+const doDefaultInit = async (basicAccounts: BasicAccounts, totalPurchaseCost: number, assetQty: number, expiresAt: number | null = null, feeBearer: object = { seller: {} }, lotSize: number = 1, rounding: object = { exact: {} }) => {
+    // Create the new account and tender it with the program, pricing each lot at its share of the total cost
+    const pricePerLot = totalPurchaseCost * lotSize / assetQty;
     const initAccountsBlock = {
       escrowAccount: basicAccounts.escrowAccount,
       escrowTokenAccount: basicAccounts.escrowTokenAccount,
       seller: basicAccounts.seller.publicKey,
-      receiver: basicAccounts.buyer.publicKey,
+      receiver: basicAccounts.receiver,
       mint: basicAccounts.mint.publicKey,
       purchaseMint: basicAccounts.purchaseMint.publicKey,
       sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
       sellFromAccount: basicAccounts.sellFromAccount.address,
-      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      tokenProgram: basicAccounts.mint.programId,
       associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -129,26 +207,235 @@ const doDefaultInit = async (basicAccounts: BasicAccounts, totalPurchaseCost: nu
     logInfo('bumpSeed:', basicAccounts.bumpSeed);
     logInfo();
 
-    await program.rpc.tender(new anchor.BN(basicAccounts.bumpSeed), new anchor.BN(totalPurchaseCost), new anchor.BN(assetQty), {
+    await program.rpc.tender(new anchor.BN(basicAccounts.escrowId), new anchor.BN(pricePerLot), new anchor.BN(lotSize), new anchor.BN(assetQty), expiresAt === null ? null : new anchor.BN(expiresAt), feeBearer, rounding, {
       accounts: initAccountsBlock,
       signers: [basicAccounts.seller],
     });
 }
 
-const doDefaultPurchase = async (basicAccounts: BasicAccounts) => {
-  const purchaseAccountsBlock = {
+const doDutchInit = async (basicAccounts: BasicAccounts, assetQty: number, startPrice: number, endPrice: number, startTime: number, endTime: number) => {
+    const initAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      seller: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.receiver,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      sellFromAccount: basicAccounts.sellFromAccount.address,
+      tokenProgram: basicAccounts.mint.programId,
+      associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    };
+    logAccounts('tenderDutch', initAccountsBlock);
+
+    await program.rpc.tenderDutch(
+      new anchor.BN(basicAccounts.escrowId), new anchor.BN(assetQty),
+      new anchor.BN(startPrice), new anchor.BN(endPrice), new anchor.BN(startTime), new anchor.BN(endTime),
+      null, { seller: {} }, {
+      accounts: initAccountsBlock,
+      signers: [basicAccounts.seller],
+    });
+}
+
+const doEnglishInit = async (basicAccounts: BasicAccounts, assetQty: number, minBid: number, endTime: number) => {
+    const bidVault = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, basicAccounts.purchaseMint.programId, basicAccounts.purchaseMint.publicKey, basicAccounts.escrowAccount, true);
+    const initAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      bidVault: bidVault,
+      seller: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.receiver,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      sellFromAccount: basicAccounts.sellFromAccount.address,
+      tokenProgram: basicAccounts.mint.programId,
+      purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+      associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    };
+    logAccounts('tenderEnglish', initAccountsBlock);
+
+    await program.rpc.tenderEnglish(new anchor.BN(basicAccounts.escrowId), new anchor.BN(assetQty), new anchor.BN(minBid), new anchor.BN(endTime), {
+      accounts: initAccountsBlock,
+      signers: [basicAccounts.seller],
+    });
+    return bidVault;
+}
+
+// The previous bidder is refunded to their associated account. It is ignored for the first bid
+const placeBid = async (basicAccounts: BasicAccounts, bidVault: anchor.web3.PublicKey, bidder: anchor.web3.Keypair, bidderAccount: anchor.web3.PublicKey, previousBidder: anchor.web3.PublicKey, amount: number) => {
+    const previousBidderAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, basicAccounts.purchaseMint.programId, basicAccounts.purchaseMint.publicKey, previousBidder);
+    return program.rpc.bid(new anchor.BN(amount), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        bidVault: bidVault,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        bidder: bidder.publicKey,
+        previousBidder: previousBidder,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        bidderAccount: bidderAccount,
+        previousBidderAccount: previousBidderAccount,
+        purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [bidder],
+    });
+}
+
+const doSealedInit = async (basicAccounts: BasicAccounts, assetQty: number, minBid: number, commitEndTime: number, revealEndTime: number) => {
+    const initAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      seller: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.receiver,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      sellFromAccount: basicAccounts.sellFromAccount.address,
+      tokenProgram: basicAccounts.mint.programId,
+      associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    };
+    logAccounts('tenderSealed', initAccountsBlock);
+
+    await program.rpc.tenderSealed(new anchor.BN(basicAccounts.escrowId), new anchor.BN(assetQty), new anchor.BN(minBid), new anchor.BN(commitEndTime), new anchor.BN(revealEndTime), {
+      accounts: initAccountsBlock,
+      signers: [basicAccounts.seller],
+    });
+}
+
+const doLazyMintInit = async (basicAccounts: BasicAccounts, mintAuthority: anchor.web3.PublicKey, pricePerLot: number, supplyCap: number) => {
+    const initAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      mintAuthority: mintAuthority,
+      payer: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.receiver,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      tokenProgram: basicAccounts.mint.programId,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    };
+    logAccounts('tenderLazyMint', initAccountsBlock);
+
+    await program.rpc.tenderLazyMint(new anchor.BN(basicAccounts.escrowId), new anchor.BN(pricePerLot), new anchor.BN(1), new anchor.BN(supplyCap), null, { seller: {} }, { exact: {} }, {
+      accounts: initAccountsBlock,
+      signers: [basicAccounts.seller],
+    });
+}
+
+const getBidCommitmentAccounts = async (basicAccounts: BasicAccounts, bidder: anchor.web3.PublicKey) => {
+    const [ bidCommitment ] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("bid"), basicAccounts.escrowAccount.toBuffer(), bidder.toBuffer()],
+      program.programId,
+    );
+    const depositVault = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, basicAccounts.purchaseMint.programId, basicAccounts.purchaseMint.publicKey, bidCommitment, true);
+    return { bidCommitment, depositVault };
+}
+
+const commitSealedBid = async (basicAccounts: BasicAccounts, bidder: anchor.web3.Keypair, bidderAccount: anchor.web3.PublicKey, bid: number, salt: Buffer, deposit: number) => {
+    const { bidCommitment, depositVault } = await getBidCommitmentAccounts(basicAccounts, bidder.publicKey);
+    const commitment = createHash('sha256')
+      .update(Buffer.concat([basicAccounts.escrowAccount.toBuffer(), bidder.publicKey.toBuffer(), new anchor.BN(bid).toArrayLike(Buffer, 'le', 8), salt]))
+      .digest();
+    await program.rpc.commitBid([...commitment], new anchor.BN(deposit), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        bidCommitment: bidCommitment,
+        depositVault: depositVault,
+        receiver: basicAccounts.receiver,
+        bidder: bidder.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        bidderAccount: bidderAccount,
+        purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [bidder],
+    });
+}
+
+const revealSealedBid = async (basicAccounts: BasicAccounts, bidder: anchor.web3.Keypair, bid: number, salt: Buffer) => {
+    const { bidCommitment } = await getBidCommitmentAccounts(basicAccounts, bidder.publicKey);
+    await program.rpc.revealBid(new anchor.BN(bid), [...salt], {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        bidCommitment: bidCommitment,
+        bidder: bidder.publicKey,
+      },
+      signers: [bidder],
+    });
+}
+
+const refundSealedBid = async (basicAccounts: BasicAccounts, bidder: anchor.web3.PublicKey, bidderAccount: anchor.web3.PublicKey) => {
+    const { bidCommitment, depositVault } = await getBidCommitmentAccounts(basicAccounts, bidder);
+    await program.rpc.refundBid({
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        bidCommitment: bidCommitment,
+        depositVault: depositVault,
+        bidder: bidder,
+        bidderAccount: bidderAccount,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+      },
+    });
+}
+
+const getCancelAccountsBlock = async (basicAccounts: BasicAccounts) => {
+  return {
+    escrowAccount: basicAccounts.escrowAccount,
+    escrowTokenAccount: basicAccounts.escrowTokenAccount,
+    bidVault: await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, basicAccounts.purchaseMint.programId, basicAccounts.purchaseMint.publicKey, basicAccounts.escrowAccount, true),
+    seller: basicAccounts.seller.publicKey,
+    receiver: basicAccounts.receiver,
+    mint: basicAccounts.mint.publicKey,
+    sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+    purchaseMint: basicAccounts.purchaseMint.publicKey,
+    sellFromAccount: basicAccounts.sellFromAccount.address,
+    tokenProgram: basicAccounts.mint.programId,
+    purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+  };
+}
+
+const getPurchaseAccountsBlock = (basicAccounts: BasicAccounts) => {
+  return {
     escrowAccount: basicAccounts.escrowAccount,
     escrowTokenAccount: basicAccounts.escrowTokenAccount,
     rentPayer: basicAccounts.seller.publicKey,
-    receiver: basicAccounts.buyer.publicKey,
+    receiver: basicAccounts.receiver,
+    recipient: basicAccounts.buyer.publicKey,
     signer: basicAccounts.buyer.publicKey,
     mint: basicAccounts.mint.publicKey,
     purchaseMint: basicAccounts.purchaseMint.publicKey,
     sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
     buyFromAccount: basicAccounts.buyFromAccount.address,
     buyToAccount: basicAccounts.buyToAccount.address,
-    tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    treasuryAccount: basicAccounts.treasuryAccount.address,
+    config: configAccount,
+    tokenProgram: basicAccounts.mint.programId,
+    purchaseTokenProgram: basicAccounts.purchaseMint.programId,
+    associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
+    rent: anchor.web3.SYSVAR_RENT_PUBKEY,
   };
+}
+
+const doDefaultPurchase = async (basicAccounts: BasicAccounts) => {
+  const purchaseAccountsBlock = getPurchaseAccountsBlock(basicAccounts);
   logAccounts('purchase', purchaseAccountsBlock);
 
   await program.rpc.purchase({
@@ -164,6 +451,35 @@ describe('escrow', () => {
   anchor.setProvider(provider);
   const connection = provider.connection;
 
+  before(async () => {
+    // The config is global, so create it once with no fee and let individual tests change it
+    [ configAccount ] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("config")], program.programId);
+    const [ programData ] = await anchor.web3.PublicKey.findProgramAddress([program.programId.toBuffer()], BPF_LOADER_UPGRADEABLE_PROGRAM_ID);
+    if (await connection.getAccountInfo(configAccount) === null && await connection.getAccountInfo(programData) !== null) {
+      const initConfigAccounts = (admin: anchor.web3.PublicKey) => ({
+        config: configAccount,
+        admin,
+        program: program.programId,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      });
+
+      // Only the upgrade authority may create the config, so nobody can claim it first
+      const impostor = anchor.web3.Keypair.generate();
+      await connection.confirmTransaction(await connection.requestAirdrop(impostor.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL));
+      await assertProgramError(() => program.rpc.initializeConfig(0, impostor.publicKey, {
+        accounts: initConfigAccounts(impostor.publicKey),
+        signers: [impostor],
+      }), 'NotUpgradeAuthority');
+
+      await program.rpc.initializeConfig(0, provider.wallet.publicKey, {
+        accounts: initConfigAccounts(provider.wallet.publicKey),
+      });
+    }
+    hasConfig = await connection.getAccountInfo(configAccount) !== null;
+  });
+
   it("Tenders an escrow account", async () => {
     const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
     const totalPurchaseCost = 200;
@@ -189,6 +505,18 @@ describe('escrow', () => {
     assert.ok(startBalances.sellerSaleToken.subn(assetQty).eq(createdBalances.sellerSaleToken));
     assert.ok(program.programId.toBase58() === (await connection.getAccountInfo(basicAccounts.escrowAccount)).owner.toBase58());
     assert.ok(accountPostInit.bumpSeed === basicAccounts.bumpSeed);
+
+    // check the escrow terms were recorded
+    assert.ok(accountPostInit.seller.equals(basicAccounts.seller.publicKey));
+    assert.ok(accountPostInit.receiver.equals(basicAccounts.buyer.publicKey));
+    assert.ok(accountPostInit.mint.equals(basicAccounts.mint.publicKey));
+    assert.ok(accountPostInit.purchaseMint.equals(basicAccounts.purchaseMint.publicKey));
+    assert.ok(accountPostInit.sellerProceedsAccount.equals(basicAccounts.sellerProceedsAccount.address));
+    assert.ok(accountPostInit.rentPayer.equals(basicAccounts.seller.publicKey));
+    assert.ok(accountPostInit.createdAtSlot.gtn(0));
+    assert.ok(accountPostInit.originalQuantity.eq(new anchor.BN(assetQty)));
+    assert.ok(accountPostInit.quantitySold.eqn(0));
+    assert.deepEqual(accountPostInit.feeBearer, { seller: {} });
   });
 
   it("Tenders and transfers an escrow account", async () => {
@@ -220,6 +548,168 @@ describe('escrow', () => {
     assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
   });
 
+  it("Tenders and transfers a Token-2022 escrow account", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    const createdBalances = await getMainBalances(basicAccounts);
+    const escrowPostInitBalance = (await basicAccounts.mint.getAccountInfo(basicAccounts.escrowTokenAccount)).amount;
+    assert.ok(escrowPostInitBalance.eq(new anchor.BN(assetQty)));
+    assert.ok((await connection.getAccountInfo(basicAccounts.escrowTokenAccount)).owner.equals(TOKEN_2022_PROGRAM_ID));
+
+    await doDefaultPurchase(basicAccounts);
+
+    // Check state post-purchase is accurate
+    const purchasedBalances = await getMainBalances(basicAccounts);
+    assert.ok(createdBalances.sellerPurchaseToken.addn(totalPurchaseCost).eq(purchasedBalances.sellerPurchaseToken));
+    assert.ok(createdBalances.buyerSaleToken.addn(assetQty).eq(purchasedBalances.buyerSaleToken));
+    assert.ok(createdBalances.buyerPurchaseToken.subn(totalPurchaseCost).eq(purchasedBalances.buyerPurchaseToken));
+
+    // Account should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Tenders and purchases on transfer fee mints with the seller bearing the fee", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const assetQty = 100;
+    const purchaseQty = 10;
+
+    // 1% of the tender is withheld, so only 99 arrive and are sold at 1 each
+    await doDefaultInit(basicAccounts, assetQty, assetQty, null, { seller: {} });
+    const tenderedAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(tenderedAccount.originalQuantity.eqn(99));
+    assert.ok(tenderedAccount.totalPurchaseCost.eqn(99));
+    assert.ok((await getTokenBalance(connection, basicAccounts.escrowTokenAccount)).eqn(99));
+
+    await program.rpc.purchasePartial(new anchor.BN(purchaseQty), new anchor.BN(10), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+
+    // The buyer pays the 10 cost and the seller receives it less the 1 withheld, and 1 of the 10 sold is withheld on its way to the buyer
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyFromAccount.address)).eqn(200 - 10));
+    assert.ok((await getTokenBalance(connection, basicAccounts.sellerProceedsAccount.address)).eqn(9));
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyToAccount.address)).eqn(9));
+    assert.ok((await getTokenBalance(connection, basicAccounts.escrowTokenAccount)).eqn(89));
+  });
+
+  it("Tenders and purchases on transfer fee mints with the buyer bearing the fee", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const assetQty = 100;
+    const purchaseQty = 10;
+
+    // 1% of the tender is withheld, so only 99 arrive and are sold at 1 each
+    await doDefaultInit(basicAccounts, assetQty, assetQty, null, { buyer: {} });
+    const tenderedAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(tenderedAccount.originalQuantity.eqn(99));
+    assert.ok(tenderedAccount.totalPurchaseCost.eqn(99));
+    assert.ok((await getTokenBalance(connection, basicAccounts.escrowTokenAccount)).eqn(99));
+
+    // The maximum covers what the buyer sends, not just the cost
+    await assertProgramError(() => program.rpc.purchasePartial(new anchor.BN(purchaseQty), new anchor.BN(10), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'MaxCostExceeded');
+    await program.rpc.purchasePartial(new anchor.BN(purchaseQty), new anchor.BN(11), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+
+    // The buyer sends 11 so that the seller receives the full 10 cost, and 1 of the 10 sold is withheld on its way to the buyer
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyFromAccount.address)).eqn(200 - 11));
+    assert.ok((await getTokenBalance(connection, basicAccounts.sellerProceedsAccount.address)).eqn(10));
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyToAccount.address)).eqn(9));
+    assert.ok((await getTokenBalance(connection, basicAccounts.escrowTokenAccount)).eqn(89));
+  });
+
+  it("Harvests withheld transfer fees so a fully purchased escrow closes", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const assetQty = 100;
+
+    // the fee withheld on the tender stays in the escrow token account after the last of the 99 is sold
+    await doDefaultInit(basicAccounts, assetQty, assetQty);
+    await program.rpc.purchase({
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyToAccount.address)).eqn(98));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Harvests withheld transfer fees so a cancelled escrow closes", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const assetQty = 100;
+
+    await doDefaultInit(basicAccounts, assetQty, assetQty);
+    await program.rpc.cancel({
+      accounts: await getCancelAccountsBlock(basicAccounts),
+      signers: [basicAccounts.seller],
+    });
+    assert.ok((await getTokenBalance(connection, basicAccounts.sellFromAccount.address)).eqn(98));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Harvests withheld transfer fees so a settled English auction closes", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const assetQty = 100;
+    const endTime = Math.floor(Date.now() / 1000) + 4;
+    const bidVault = await doEnglishInit(basicAccounts, assetQty, 40, endTime);
+
+    // fees are withheld both in the escrow token account and in the bid vault
+    const buyer = basicAccounts.buyer;
+    await placeBid(basicAccounts, bidVault, buyer, basicAccounts.buyFromAccount.address, buyer.publicKey, 50);
+
+    await new Promise((resolve) => setTimeout(resolve, 6000));
+    const winnerTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, basicAccounts.mint.publicKey, buyer.publicKey);
+    await program.rpc.settle({
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        bidVault: bidVault,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        winner: buyer.publicKey,
+        signer: provider.wallet.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        treasuryAccount: basicAccounts.treasuryAccount.address,
+        winnerTokenAccount: winnerTokenAccount,
+        config: configAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        purchaseTokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+    assert.ok((await getTokenBalance(connection, winnerTokenAccount)).eqn(98));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+    assert.ok(await connection.getAccountInfo(bidVault) === null);
+  });
+
+  it("Rejects a later tender with a different fee bearer", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+
+    await doDefaultInit(basicAccounts, 100, 5);
+    await assertProgramError(() => doDefaultInit(basicAccounts, 100, 5, null, { buyer: {} }), 'TermsMismatch');
+
+    const escrowBalance = (await basicAccounts.mint.getAccountInfo(basicAccounts.escrowTokenAccount)).amount;
+    assert.ok(escrowBalance.eqn(5));
+  });
+
   it("Tenders and cancels an escrow account", async () => {
     const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
     const totalPurchaseCost = 200;
@@ -237,17 +727,7 @@ describe('escrow', () => {
     logInfo();
 
     // cancel it
-    const cancelAccountsBlock = {
-      escrowAccount: basicAccounts.escrowAccount,
-      escrowTokenAccount: basicAccounts.escrowTokenAccount,
-      seller: basicAccounts.seller.publicKey,
-      receiver: basicAccounts.buyer.publicKey,
-      mint: basicAccounts.mint.publicKey,
-      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
-      purchaseMint: basicAccounts.purchaseMint.publicKey,
-      sellFromAccount: basicAccounts.sellFromAccount.address,
-      tokenProgram: splToken.TOKEN_PROGRAM_ID,
-    };
+    const cancelAccountsBlock = await getCancelAccountsBlock(basicAccounts);
     logAccounts('cancel', cancelAccountsBlock);
 
     logInfo('Starting cancel');
@@ -293,17 +773,9 @@ describe('escrow', () => {
 
     // purchase on behalf
     const purchaseAccountsBlock = {
-      escrowAccount: basicAccounts.escrowAccount,
-      escrowTokenAccount: basicAccounts.escrowTokenAccount,
-      rentPayer: basicAccounts.seller.publicKey,
-      receiver: basicAccounts.buyer.publicKey,
+      ...getPurchaseAccountsBlock(basicAccounts),
       signer: payer.publicKey,
-      mint: basicAccounts.mint.publicKey,
-      purchaseMint: basicAccounts.purchaseMint.publicKey,
-      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
       buyFromAccount: buyFromAccount.address,
-      buyToAccount: basicAccounts.buyToAccount.address,
-      tokenProgram: splToken.TOKEN_PROGRAM_ID,
     };
     logAccounts('purchase', purchaseAccountsBlock);
 
@@ -346,17 +818,11 @@ describe('escrow', () => {
 
     // purchase to wrong account
     const purchaseAccountsBlock = {
-      escrowAccount: basicAccounts.escrowAccount,
-      escrowTokenAccount: basicAccounts.escrowTokenAccount,
-      rentPayer: basicAccounts.seller.publicKey,
-      receiver: basicAccounts.buyer.publicKey,
+      ...getPurchaseAccountsBlock(basicAccounts),
+      recipient: payer.publicKey,
       signer: payer.publicKey,
-      mint: basicAccounts.mint.publicKey,
-      purchaseMint: basicAccounts.purchaseMint.publicKey,
-      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
       buyFromAccount: buyFromAccount.address,
       buyToAccount: buyToAccount.address,
-      tokenProgram: splToken.TOKEN_PROGRAM_ID,
     };
     logAccounts('purchase', purchaseAccountsBlock);
 
@@ -402,7 +868,7 @@ describe('escrow', () => {
     logInfo('bumpSeed:', basicAccounts.bumpSeed);
     logInfo();
 
-    await program.rpc.tenderFromMint(new anchor.BN(basicAccounts.bumpSeed), new anchor.BN(totalPurchaseCost), new anchor.BN(assetQty), {
+    await program.rpc.tenderFromMint(new anchor.BN(basicAccounts.escrowId), new anchor.BN(totalPurchaseCost / assetQty), new anchor.BN(1), new anchor.BN(assetQty), null, { seller: {} }, { exact: {} }, {
       accounts: initAccountsBlock,
       signers: [basicAccounts.seller],
     });
@@ -445,22 +911,10 @@ describe('escrow', () => {
     logInfo();
 
     // purchase to other
-    const purchaseAccountsBlock = {
-      escrowAccount: basicAccounts.escrowAccount,
-      escrowTokenAccount: basicAccounts.escrowTokenAccount,
-      rentPayer: basicAccounts.seller.publicKey,
-      receiver: basicAccounts.buyer.publicKey,
-      signer: basicAccounts.buyer.publicKey,
-      mint: basicAccounts.mint.publicKey,
-      purchaseMint: basicAccounts.purchaseMint.publicKey,
-      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
-      buyFromAccount: basicAccounts.buyFromAccount.address,
-      buyToAccount: basicAccounts.buyToAccount.address,
-      tokenProgram: splToken.TOKEN_PROGRAM_ID,
-      };
+    const purchaseAccountsBlock = getPurchaseAccountsBlock(basicAccounts);
     logAccounts('purchase', purchaseAccountsBlock);
 
-    await program.rpc.purchasePartial(new anchor.BN(initialPurchase), {
+    await program.rpc.purchasePartial(new anchor.BN(initialPurchase), new anchor.BN(purchasePricePerUnit * initialPurchase), {
       accounts: purchaseAccountsBlock,
       signers: [basicAccounts.buyer],
     });
@@ -480,11 +934,14 @@ describe('escrow', () => {
     // Account should still be open
     assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) !== null);
     assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) !== null);
+    const partialAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(partialAccount.quantitySold.eq(new anchor.BN(initialPurchase)));
+    assert.ok(partialAccount.originalQuantity.eq(new anchor.BN(assetQty)));
 
     // purchase remainder
     let remaining = assetQty - initialPurchase;
     logInfo('Trying to purchase', remaining)
-    await program.rpc.purchasePartial(new anchor.BN(remaining), {
+    await program.rpc.purchasePartial(new anchor.BN(remaining), new anchor.BN(purchasePricePerUnit * remaining), {
       accounts: purchaseAccountsBlock,
       signers: [basicAccounts.buyer],
     });
@@ -533,7 +990,7 @@ describe('escrow', () => {
     logInfo('bumpSeed:', basicAccounts.bumpSeed);
     logInfo();
 
-    await program.rpc.tenderFromMint(new anchor.BN(basicAccounts.bumpSeed), new anchor.BN(totalPurchaseCost), new anchor.BN(assetQty), {
+    await program.rpc.tenderFromMint(new anchor.BN(basicAccounts.escrowId), new anchor.BN(totalPurchaseCost / assetQty), new anchor.BN(1), new anchor.BN(assetQty), null, { seller: {} }, { exact: {} }, {
       accounts: initAccountsBlock,
     });
 
@@ -575,6 +1032,36 @@ describe('escrow', () => {
     assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
   });
 
+  it("Stops charging for burned tokens of a fixed price escrow", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const assetQty = 10;
+
+    // 10 at 20 each, then burn 9 of them
+    await doDefaultInit(basicAccounts, 200, assetQty);
+    await program.rpc.burn(new anchor.BN(9), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+      signers: [basicAccounts.seller],
+    });
+    assert.ok((await program.account.escrowAccount.fetch(basicAccounts.escrowAccount)).totalPurchaseCost.eqn(20));
+
+    // the last token still costs its lot price
+    await program.rpc.purchaseWithMaxCost(new anchor.BN(20), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.buyFromAccount.address)).amount.eqn(180));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
   it("Tenders an escrow account twice", async () => {
     const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
     const totalPurchaseCost = 200;
@@ -603,4 +1090,1101 @@ describe('escrow', () => {
     assert.ok(accountPostInit.bumpSeed === basicAccounts.bumpSeed);
   });
 
+  it("Emits events for tender and purchase", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    const events = {};
+    const listeners = ['EscrowTendered', 'EscrowPurchased', 'EscrowClosed'].map((name) =>
+      program.addEventListener(name, (event, _slot) => { events[name] = event; })
+    );
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    await doDefaultPurchase(basicAccounts);
+
+    // give the websocket a moment to deliver the logs
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    for (const listener of listeners) {
+      await program.removeEventListener(listener);
+    }
+
+    assert.ok(events['EscrowTendered'].escrow.equals(basicAccounts.escrowAccount));
+    assert.ok(events['EscrowTendered'].escrowId.eqn(basicAccounts.escrowId));
+    assert.ok(events['EscrowTendered'].quantity.eq(new anchor.BN(assetQty)));
+    assert.ok(events['EscrowTendered'].totalPurchaseCost.eq(new anchor.BN(totalPurchaseCost)));
+    assert.ok(events['EscrowPurchased'].buyer.equals(basicAccounts.buyer.publicKey));
+    assert.ok(events['EscrowPurchased'].cost.eq(new anchor.BN(totalPurchaseCost)));
+    assert.ok(events['EscrowPurchased'].totalPurchaseCost.eqn(0));
+    assert.ok(events['EscrowClosed'].escrow.equals(basicAccounts.escrowAccount));
+    assert.ok(events['EscrowClosed'].escrowId.eqn(basicAccounts.escrowId));
+  });
+
+  it("Rejects purchases after expiry and closes the expired escrow", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    const startBalances = await getMainBalances(basicAccounts);
+    const expiresAt = Math.floor(Date.now() / 1000) + 2;
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty, expiresAt);
+
+    const accountPostInit = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(accountPostInit.expiresAt.eq(new anchor.BN(expiresAt)));
+
+    // wait for the deadline to pass
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await assertProgramError(() => doDefaultPurchase(basicAccounts), 'EscrowExpired');
+
+    // anyone can close an expired escrow
+    const closeAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      rentPayer: basicAccounts.seller.publicKey,
+      seller: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.buyer.publicKey,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      sellFromAccount: basicAccounts.sellFromAccount.address,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+    logAccounts('close expired', closeAccountsBlock);
+    await program.rpc.closeExpired({
+      accounts: closeAccountsBlock,
+    });
+
+    const closedBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.sellerSaleToken.eq(closedBalances.sellerSaleToken));
+    assert.ok(startBalances.buyerPurchaseToken.eq(closedBalances.buyerPurchaseToken));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Purchases an open listing to any account", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    const createdBalances = await getMainBalances(basicAccounts);
+    const accountPostInit = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(accountPostInit.receiver.equals(anchor.web3.SystemProgram.programId));
+
+    // the buyer's own account is not the receiver, but open listings deliver anywhere
+    await doDefaultPurchase(basicAccounts);
+
+    const purchasedBalances = await getMainBalances(basicAccounts);
+    assert.ok(createdBalances.sellerPurchaseToken.addn(totalPurchaseCost).eq(purchasedBalances.sellerPurchaseToken));
+    assert.ok(createdBalances.buyerSaleToken.addn(assetQty).eq(purchasedBalances.buyerSaleToken));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Pays the protocol fee to the treasury", async function () {
+    if (!hasConfig) {
+      this.skip();
+    }
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+    const feeBasisPoints = 250;
+    const expectedFee = totalPurchaseCost * feeBasisPoints / 10000;
+
+    const configAccounts = {
+      config: configAccount,
+      admin: provider.wallet.publicKey,
+    };
+    await program.rpc.setFee(feeBasisPoints, { accounts: configAccounts });
+
+    try {
+      await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+      const createdBalances = await getMainBalances(basicAccounts);
+      const treasuryCreatedBalance = (await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.treasuryAccount.address)).amount;
+
+      await doDefaultPurchase(basicAccounts);
+
+      const purchasedBalances = await getMainBalances(basicAccounts);
+      const treasuryPurchasedBalance = (await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.treasuryAccount.address)).amount;
+      assert.ok(createdBalances.buyerPurchaseToken.subn(totalPurchaseCost).eq(purchasedBalances.buyerPurchaseToken));
+      assert.ok(createdBalances.sellerPurchaseToken.addn(totalPurchaseCost - expectedFee).eq(purchasedBalances.sellerPurchaseToken));
+      assert.ok(treasuryCreatedBalance.addn(expectedFee).eq(treasuryPurchasedBalance));
+    } finally {
+      await program.rpc.setFee(0, { accounts: configAccounts });
+    }
+  });
+
+  it("Doesn't need a treasury account while the fee is zero", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    const createdBalances = await getMainBalances(basicAccounts);
+
+    await program.rpc.purchase({
+      accounts: {
+        ...getPurchaseAccountsBlock(basicAccounts),
+        treasuryAccount: anchor.web3.Keypair.generate().publicKey,
+      },
+      signers: [basicAccounts.buyer],
+    });
+
+    const purchasedBalances = await getMainBalances(basicAccounts);
+    assert.ok(createdBalances.sellerPurchaseToken.addn(totalPurchaseCost).eq(purchasedBalances.sellerPurchaseToken));
+    assert.ok(createdBalances.buyerSaleToken.addn(assetQty).eq(purchasedBalances.buyerSaleToken));
+  });
+
+  it("Rejects config updates from anyone but the admin", async function () {
+    if (!hasConfig) {
+      this.skip();
+    }
+    const impostor = anchor.web3.Keypair.generate();
+    await assertProgramError(() => program.rpc.setTreasury(impostor.publicKey, {
+      accounts: {
+        config: configAccount,
+        admin: impostor.publicKey,
+      },
+      signers: [impostor],
+    }), 'NotAdmin');
+    const config = await program.account.configAccount.fetch(configAccount);
+    assert.ok(config.treasury.equals(provider.wallet.publicKey));
+  });
+
+  it("Creates the receiver's token account on purchase", async () => {
+    const giftee = anchor.web3.Keypair.generate();
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, giftee.publicKey);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+
+    const gifteeTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, splToken.TOKEN_PROGRAM_ID, basicAccounts.mint.publicKey, giftee.publicKey);
+    assert.ok(await connection.getAccountInfo(gifteeTokenAccount) === null);
+
+    const purchaseAccountsBlock = {
+      ...getPurchaseAccountsBlock(basicAccounts),
+      receiver: giftee.publicKey,
+      recipient: giftee.publicKey,
+      buyToAccount: gifteeTokenAccount,
+    };
+    logAccounts('purchase', purchaseAccountsBlock);
+    await program.rpc.purchase({
+      accounts: purchaseAccountsBlock,
+      signers: [basicAccounts.buyer],
+    });
+
+    const gifteeBalance = (await basicAccounts.mint.getAccountInfo(gifteeTokenAccount)).amount;
+    assert.ok(gifteeBalance.eq(new anchor.BN(assetQty)));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Purchases with native SOL", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, true);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    const createdBalances = await getMainBalances(basicAccounts);
+    const buyerCreatedLamports = await connection.getBalance(basicAccounts.buyer.publicKey);
+
+    // the buyer pays in lamports, with no wrapped SOL of their own
+    const purchaseAccountsBlock = getPurchaseAccountsBlock(basicAccounts);
+    logAccounts('purchase with sol', purchaseAccountsBlock);
+    await program.rpc.purchaseWithSol(new anchor.BN(totalPurchaseCost), {
+      accounts: purchaseAccountsBlock,
+      signers: [basicAccounts.buyer],
+    });
+
+    const purchasedBalances = await getMainBalances(basicAccounts);
+    const buyerPurchasedLamports = await connection.getBalance(basicAccounts.buyer.publicKey);
+    assert.ok(createdBalances.sellerPurchaseToken.addn(totalPurchaseCost).eq(purchasedBalances.sellerPurchaseToken));
+    assert.ok(createdBalances.buyerSaleToken.addn(assetQty).eq(purchasedBalances.buyerSaleToken));
+    assert.ok(createdBalances.buyerPurchaseToken.eq(purchasedBalances.buyerPurchaseToken));
+    assert.ok(buyerCreatedLamports - buyerPurchasedLamports >= totalPurchaseCost);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Rejects a second tender at a different price", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    await assertProgramError(() => doDefaultInit(basicAccounts, totalPurchaseCost / 2, assetQty), 'PriceRatioMismatch');
+
+    const resultingAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(resultingAccount.totalPurchaseCost.eq(new anchor.BN(totalPurchaseCost)));
+  });
+
+  it("Rejects a partial purchase that isn't a whole number of lots", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 10;
+    const lotSize = 5;
+
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty, null, { seller: {} }, lotSize);
+
+    await assertProgramError(() => program.rpc.purchasePartial(new anchor.BN(1), new anchor.BN(totalPurchaseCost), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'NonDivisiblePurchase');
+  });
+
+  it("Purchases from a Dutch auction at its start and end prices", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const assetQty = 5;
+    const startPrice = 10;
+    const endPrice = 4;
+
+    // Before the auction starts the unit price is the start price
+    const upcoming: BasicAccounts = await getBasicAccounts(provider);
+    await doDutchInit(upcoming, assetQty, startPrice, endPrice, now + 1000, now + 2000);
+    const upcomingAccount = await program.account.escrowAccount.fetch(upcoming.escrowAccount);
+    assert.ok(upcomingAccount.pricingMode.dutchAuction.startPrice.eqn(startPrice));
+    const upcomingCreated = await getMainBalances(upcoming);
+    await doDefaultPurchase(upcoming);
+    const upcomingPurchased = await getMainBalances(upcoming);
+    assert.ok(upcomingCreated.sellerPurchaseToken.addn(assetQty * startPrice).eq(upcomingPurchased.sellerPurchaseToken));
+    assert.ok(upcomingCreated.buyerSaleToken.addn(assetQty).eq(upcomingPurchased.buyerSaleToken));
+
+    // After it ends the unit price is the end price
+    const ended: BasicAccounts = await getBasicAccounts(provider);
+    await doDutchInit(ended, assetQty, startPrice, endPrice, now - 2000, now - 1000);
+    const endedCreated = await getMainBalances(ended);
+    await doDefaultPurchase(ended);
+    const endedPurchased = await getMainBalances(ended);
+    assert.ok(endedCreated.sellerPurchaseToken.addn(assetQty * endPrice).eq(endedPurchased.sellerPurchaseToken));
+    assert.ok(endedCreated.buyerPurchaseToken.subn(assetQty * endPrice).eq(endedPurchased.buyerPurchaseToken));
+
+    // Account should be closed
+    assert.ok(await connection.getAccountInfo(ended.escrowAccount) === null);
+  });
+
+  it("Rejects a Dutch auction whose price rises", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const now = Math.floor(Date.now() / 1000);
+
+    await assertProgramError(() => doDutchInit(basicAccounts, 5, 4, 10, now, now + 1000), 'InvalidAuctionPrices');
+  });
+
+  it("Settles an English auction to the highest bidder", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const assetQty = 10;
+    const minBid = 40;
+    const endTime = Math.floor(Date.now() / 1000) + 4;
+    const bidVault = await doEnglishInit(basicAccounts, assetQty, minBid, endTime);
+
+    // a second bidder to outbid the buyer
+    const rival = anchor.web3.Keypair.generate();
+    await connection.confirmTransaction(await connection.requestAirdrop(rival.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL));
+    const rivalAccount = await basicAccounts.purchaseMint.getOrCreateAssociatedAccountInfo(rival.publicKey);
+    await basicAccounts.purchaseMint.mintTo(rivalAccount.address, provider.wallet.publicKey, [], 200);
+
+    const buyer = basicAccounts.buyer;
+    await assertProgramError(() => placeBid(basicAccounts, bidVault, buyer, basicAccounts.buyFromAccount.address, buyer.publicKey, minBid - 1), 'BidTooLow');
+    await placeBid(basicAccounts, bidVault, buyer, basicAccounts.buyFromAccount.address, buyer.publicKey, 50);
+    await placeBid(basicAccounts, bidVault, rival, rivalAccount.address, buyer.publicKey, 60);
+    await assertProgramError(() => placeBid(basicAccounts, bidVault, buyer, basicAccounts.buyFromAccount.address, rival.publicKey, 55), 'BidTooLow');
+
+    // the outbid buyer is refunded
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.buyFromAccount.address)).amount.eqn(200));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(rivalAccount.address)).amount.eqn(140));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(bidVault)).amount.eqn(60));
+
+    // wait for the auction to end, then anyone can settle it
+    await new Promise((resolve) => setTimeout(resolve, 6000));
+    const sellerProceedsBefore = (await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount;
+    const winnerTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, splToken.TOKEN_PROGRAM_ID, basicAccounts.mint.publicKey, rival.publicKey);
+    await program.rpc.settle({
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        bidVault: bidVault,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        winner: rival.publicKey,
+        signer: provider.wallet.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        treasuryAccount: basicAccounts.treasuryAccount.address,
+        winnerTokenAccount: winnerTokenAccount,
+        config: configAccount,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        purchaseTokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    assert.ok((await basicAccounts.mint.getAccountInfo(winnerTokenAccount)).amount.eqn(assetQty));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount.eq(sellerProceedsBefore.addn(60)));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+    assert.ok(await connection.getAccountInfo(bidVault) === null);
+  });
+
+  it("Refunds an outbid bidder whose token account was closed", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const bidVault = await doEnglishInit(basicAccounts, 10, 40, Math.floor(Date.now() / 1000) + 1000);
+
+    const rival = anchor.web3.Keypair.generate();
+    await connection.confirmTransaction(await connection.requestAirdrop(rival.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL));
+    const rivalAccount = await basicAccounts.purchaseMint.getOrCreateAssociatedAccountInfo(rival.publicKey);
+    await basicAccounts.purchaseMint.mintTo(rivalAccount.address, provider.wallet.publicKey, [], 200);
+
+    // the buyer bids, then empties and closes the account the refund is owed to
+    const buyer = basicAccounts.buyer;
+    const buyerAccount = basicAccounts.buyFromAccount.address;
+    await placeBid(basicAccounts, bidVault, buyer, buyerAccount, buyer.publicKey, 50);
+    await basicAccounts.purchaseMint.transfer(buyerAccount, rivalAccount.address, buyer, [], 150);
+    await basicAccounts.purchaseMint.closeAccount(buyerAccount, buyer.publicKey, buyer, []);
+    assert.ok(await connection.getAccountInfo(buyerAccount) === null);
+
+    // outbidding still succeeds, and recreates the account for the refund
+    await placeBid(basicAccounts, bidVault, rival, rivalAccount.address, buyer.publicKey, 60);
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(buyerAccount)).amount.eqn(50));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(bidVault)).amount.eqn(60));
+  });
+
+  it("Cancels an English auction without bids and closes the bid vault", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const assetQty = 10;
+    const bidVault = await doEnglishInit(basicAccounts, assetQty, 40, Math.floor(Date.now() / 1000) + 1000);
+    const sellFromBefore = (await basicAccounts.mint.getAccountInfo(basicAccounts.sellFromAccount.address)).amount;
+
+    await program.rpc.cancel({
+      accounts: await getCancelAccountsBlock(basicAccounts),
+      signers: [basicAccounts.seller],
+    });
+
+    assert.ok((await basicAccounts.mint.getAccountInfo(basicAccounts.sellFromAccount.address)).amount.eq(sellFromBefore.addn(assetQty)));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+    assert.ok(await connection.getAccountInfo(bidVault) === null);
+  });
+
+  it("Settles a sealed-bid auction to the highest revealed bid and refunds the loser", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const assetQty = 10;
+    const minBid = 20;
+    const now = Math.floor(Date.now() / 1000);
+    await doSealedInit(basicAccounts, assetQty, minBid, now + 4, now + 8);
+
+    // a second bidder to outbid the buyer
+    const rival = anchor.web3.Keypair.generate();
+    await connection.confirmTransaction(await connection.requestAirdrop(rival.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL));
+    const rivalAccount = await basicAccounts.purchaseMint.getOrCreateAssociatedAccountInfo(rival.publicKey);
+    await basicAccounts.purchaseMint.mintTo(rivalAccount.address, provider.wallet.publicKey, [], 200);
+
+    // both commit the same deposit, so the deposits don't give the bids away
+    const buyerSalt = randomBytes(32);
+    const rivalSalt = randomBytes(32);
+    await commitSealedBid(basicAccounts, basicAccounts.buyer, basicAccounts.buyFromAccount.address, 50, buyerSalt, 100);
+    await commitSealedBid(basicAccounts, rival, rivalAccount.address, 70, rivalSalt, 100);
+    await assertProgramError(() => revealSealedBid(basicAccounts, rival, 70, rivalSalt), 'NotInRevealPhase');
+
+    // reveal once the commit window closes
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+    await assertProgramError(() => revealSealedBid(basicAccounts, basicAccounts.buyer, 60, buyerSalt), 'InvalidReveal');
+    await revealSealedBid(basicAccounts, basicAccounts.buyer, 50, buyerSalt);
+    await revealSealedBid(basicAccounts, rival, 70, rivalSalt);
+    const revealedAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(revealedAccount.highestBid.eqn(70));
+    assert.ok(revealedAccount.highestBidder.equals(rival.publicKey));
+
+    // settle once the reveal window closes
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    const sellerProceedsBefore = (await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount;
+    const winnerTokenAccount = await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, splToken.TOKEN_PROGRAM_ID, basicAccounts.mint.publicKey, rival.publicKey);
+    const rivalBid = await getBidCommitmentAccounts(basicAccounts, rival.publicKey);
+    await program.rpc.settleSealed({
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        bidCommitment: rivalBid.bidCommitment,
+        depositVault: rivalBid.depositVault,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        winner: rival.publicKey,
+        signer: provider.wallet.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        treasuryAccount: basicAccounts.treasuryAccount.address,
+        winnerBidderAccount: rivalAccount.address,
+        winnerTokenAccount: winnerTokenAccount,
+        config: configAccount,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        purchaseTokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    assert.ok((await basicAccounts.mint.getAccountInfo(winnerTokenAccount)).amount.eqn(assetQty));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount.eq(sellerProceedsBefore.addn(70)));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(rivalAccount.address)).amount.eqn(130));
+
+    // the losing deposit is refunded in full
+    const buyerBid = await getBidCommitmentAccounts(basicAccounts, basicAccounts.buyer.publicKey);
+    await refundSealedBid(basicAccounts, basicAccounts.buyer.publicKey, basicAccounts.buyFromAccount.address);
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.buyFromAccount.address)).amount.eqn(200));
+
+    // Accounts should be closed
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(rivalBid.bidCommitment) === null);
+    assert.ok(await connection.getAccountInfo(buyerBid.bidCommitment) === null);
+    assert.ok(await connection.getAccountInfo(buyerBid.depositVault) === null);
+  });
+
+  it("Forfeits an unrevealed sealed bid and only cancels once the deposits are returned", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const assetQty = 10;
+    const now = Math.floor(Date.now() / 1000);
+    await doSealedInit(basicAccounts, assetQty, 20, now + 4, now + 8);
+
+    // once a bid is committed the seller can't pull the auction, before or after the commit window closes
+    await commitSealedBid(basicAccounts, basicAccounts.buyer, basicAccounts.buyFromAccount.address, 50, randomBytes(32), 100);
+    const cancelAccountsBlock = await getCancelAccountsBlock(basicAccounts);
+    const cancel = () => program.rpc.cancel({ accounts: cancelAccountsBlock, signers: [basicAccounts.seller] });
+    await assertProgramError(cancel, 'AuctionHasCommitments');
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+    await assertProgramError(cancel, 'AuctionHasCommitments');
+
+    // the bid is never revealed, so its deposit goes to the seller, and until then the auction can't be cancelled
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await assertProgramError(cancel, 'AuctionHasCommitments');
+    const sellerProceedsBefore = (await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount;
+    await refundSealedBid(basicAccounts, basicAccounts.buyer.publicKey, basicAccounts.buyFromAccount.address);
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.sellerProceedsAccount.address)).amount.eq(sellerProceedsBefore.addn(100)));
+    assert.ok((await basicAccounts.purchaseMint.getAccountInfo(basicAccounts.buyFromAccount.address)).amount.eqn(100));
+
+    // with the deposit returned and no bid revealed, the seller can now cancel
+    const sellFromBefore = (await basicAccounts.mint.getAccountInfo(basicAccounts.sellFromAccount.address)).amount;
+    await cancel();
+    assert.ok((await basicAccounts.mint.getAccountInfo(basicAccounts.sellFromAccount.address)).amount.eq(sellFromBefore.addn(assetQty)));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Won't burn a sealed-bid auction until its deposits are returned", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, anchor.web3.SystemProgram.programId);
+    const assetQty = 10;
+    const now = Math.floor(Date.now() / 1000);
+    await doSealedInit(basicAccounts, assetQty, 20, now + 4, now + 8);
+    await commitSealedBid(basicAccounts, basicAccounts.buyer, basicAccounts.buyFromAccount.address, 50, randomBytes(32), 100);
+
+    // burning would close the escrow and leave the deposit to be forfeited without a sale
+    const burnAccountsBlock = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      rentPayer: basicAccounts.seller.publicKey,
+      receiver: basicAccounts.receiver,
+      mint: basicAccounts.mint.publicKey,
+      purchaseMint: basicAccounts.purchaseMint.publicKey,
+      sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+    const burn = () => program.rpc.burn(new anchor.BN(assetQty), { accounts: burnAccountsBlock, signers: [basicAccounts.seller] });
+    await assertProgramError(burn, 'AuctionHasCommitments');
+    await new Promise((resolve) => setTimeout(resolve, 9000));
+    await assertProgramError(burn, 'AuctionHasCommitments');
+
+    // once the unrevealed deposit is forfeited to the still open auction, the seller can burn it
+    await refundSealedBid(basicAccounts, basicAccounts.buyer.publicKey, basicAccounts.buyFromAccount.address);
+    assert.ok((await program.account.escrowAccount.fetch(basicAccounts.escrowAccount)).commitmentCount.eqn(0));
+    await burn();
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Rejects purchases that cost more than the buyer's maximum", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 100;
+    const assetQty = 10;
+
+    // the buyer quotes the whole escrow, then the seller tenders more before the purchase lands
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+    const quotedCost = new anchor.BN(totalPurchaseCost);
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty);
+
+    const purchaseAccountsBlock = getPurchaseAccountsBlock(basicAccounts);
+    await assertProgramError(() => program.rpc.purchaseWithMaxCost(quotedCost, {
+      accounts: purchaseAccountsBlock,
+      signers: [basicAccounts.buyer],
+    }), 'MaxCostExceeded');
+    await assertProgramError(() => program.rpc.purchasePartial(new anchor.BN(assetQty), quotedCost.subn(1), {
+      accounts: purchaseAccountsBlock,
+      signers: [basicAccounts.buyer],
+    }), 'MaxCostExceeded');
+
+    // the quoted quantity is still available at the quoted cost
+    const startBalances = await getMainBalances(basicAccounts);
+    await program.rpc.purchasePartial(new anchor.BN(assetQty), quotedCost, {
+      accounts: purchaseAccountsBlock,
+      signers: [basicAccounts.buyer],
+    });
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerPurchaseToken.sub(quotedCost).eq(finalBalances.buyerPurchaseToken));
+    assert.ok(startBalances.buyerSaleToken.addn(assetQty).eq(finalBalances.buyerSaleToken));
+  });
+
+  it("Purchases as much as a budget affords", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const purchasePricePerUnit = 20;
+    const assetQty = 10;
+    const budget = 75;
+
+    await doDefaultInit(basicAccounts, purchasePricePerUnit * assetQty, assetQty);
+    const startBalances = await getMainBalances(basicAccounts);
+
+    await program.rpc.purchaseWithBudget(new anchor.BN(budget), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+
+    // 3 units fit in the budget, and only their cost is charged
+    const affordable = Math.floor(budget / purchasePricePerUnit);
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerSaleToken.addn(affordable).eq(finalBalances.buyerSaleToken));
+    assert.ok(startBalances.buyerPurchaseToken.subn(purchasePricePerUnit * affordable).eq(finalBalances.buyerPurchaseToken));
+    assert.ok(startBalances.sellerPurchaseToken.addn(purchasePricePerUnit * affordable).eq(finalBalances.sellerPurchaseToken));
+  });
+
+  it("Counts the buyer's transfer fee against a budget", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, undefined, undefined, false, TOKEN_2022_PROGRAM_ID, 0, 100);
+    const budget = 22;
+
+    // 99 of the 100 tendered arrive after the 1% transfer fee, sold at 1 each
+    await doDefaultInit(basicAccounts, 100, 100, null, { buyer: {} });
+
+    await program.rpc.purchaseWithBudget(new anchor.BN(budget), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+
+    // 22 units would need 23 sent, so 21 are bought for the whole budget; 1 of them is withheld on its way to the buyer
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyFromAccount.address)).eqn(200 - budget));
+    assert.ok((await getTokenBalance(connection, basicAccounts.sellerProceedsAccount.address)).eqn(21));
+    assert.ok((await getTokenBalance(connection, basicAccounts.buyToAccount.address)).eqn(20));
+    assert.ok((await getTokenBalance(connection, basicAccounts.escrowTokenAccount)).eqn(99 - 21));
+  });
+
+  it("Rejects a budget below the smallest purchasable quantity", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+
+    // lots of 5 units for 100 each
+    await doDefaultInit(basicAccounts, 200, 10, null, { seller: {} }, 5);
+    await assertProgramError(() => program.rpc.purchaseWithBudget(new anchor.BN(99), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'InsufficientBudget');
+  });
+
+  it("Rounds part lot purchases up and sweeps the remaining cost on the last", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const totalPurchaseCost = 200;
+    const assetQty = 3;
+
+    // a single lot of 3 units for 200
+    await doDefaultInit(basicAccounts, totalPurchaseCost, assetQty, null, { seller: {} }, assetQty, { roundUp: {} });
+    const startBalances = await getMainBalances(basicAccounts);
+
+    // 1 unit's share of 200 rounds up to 67, twice, and the last unit pays the remaining 66
+    for (const expectedCost of [67, 67, 66]) {
+      const before = await getMainBalances(basicAccounts);
+      await program.rpc.purchasePartial(new anchor.BN(1), new anchor.BN(expectedCost), {
+        accounts: getPurchaseAccountsBlock(basicAccounts),
+        signers: [basicAccounts.buyer],
+      });
+      const after = await getMainBalances(basicAccounts);
+      assert.ok(before.buyerPurchaseToken.subn(expectedCost).eq(after.buyerPurchaseToken));
+    }
+
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.sellerPurchaseToken.addn(totalPurchaseCost).eq(finalBalances.sellerPurchaseToken));
+    assert.ok(startBalances.buyerPurchaseToken.subn(totalPurchaseCost).eq(finalBalances.buyerPurchaseToken));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Rejects rounded down part lot purchases below the minimum cost", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+
+    // 1 unit of a 3 unit lot for 200 rounds down to 66
+    await doDefaultInit(basicAccounts, 200, 3, null, { seller: {} }, 3, { roundDown: { minCost: new anchor.BN(70) } });
+    await assertProgramError(() => program.rpc.purchasePartial(new anchor.BN(1), new anchor.BN(200), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'PurchaseBelowMinimum');
+
+    // 2 units round down to 133
+    const startBalances = await getMainBalances(basicAccounts);
+    await program.rpc.purchasePartial(new anchor.BN(2), new anchor.BN(200), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerPurchaseToken.subn(133).eq(finalBalances.buyerPurchaseToken));
+  });
+
+  it("Rejects a budget that only affords a rounded down purchase below the minimum cost", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    await doDefaultInit(basicAccounts, 200, 3, null, { seller: {} }, 3, { roundDown: { minCost: new anchor.BN(70) } });
+
+    // 100 only affords 1 unit at 66
+    await assertProgramError(() => program.rpc.purchaseWithBudget(new anchor.BN(100), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'InsufficientBudget');
+
+    // 150 affords 2 units at 133
+    const startBalances = await getMainBalances(basicAccounts);
+    await program.rpc.purchaseWithBudget(new anchor.BN(150), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerSaleToken.addn(2).eq(finalBalances.buyerSaleToken));
+    assert.ok(startBalances.buyerPurchaseToken.subn(133).eq(finalBalances.buyerPurchaseToken));
+  });
+
+  it("Tenders and purchases whole lots at a fixed price", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const pricePerLot = 30;
+    const lotSize = 4;
+    const assetQty = 12;
+
+    await doDefaultInit(basicAccounts, pricePerLot * assetQty / lotSize, assetQty, null, { seller: {} }, lotSize);
+    const tenderedAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(tenderedAccount.pricePerLot.eqn(pricePerLot));
+    assert.ok(tenderedAccount.lotSize.eqn(lotSize));
+    assert.ok(tenderedAccount.totalPurchaseCost.eqn(pricePerLot * assetQty / lotSize));
+
+    // later tenders must be whole lots at the same lot size and price
+    await assertProgramError(() => doDefaultInit(basicAccounts, pricePerLot * 3 / lotSize, 3, null, { seller: {} }, lotSize), 'NonDivisiblePurchase');
+    await assertProgramError(() => doDefaultInit(basicAccounts, pricePerLot * 2, lotSize * 4, null, { seller: {} }, lotSize * 2), 'PriceRatioMismatch');
+
+    const startBalances = await getMainBalances(basicAccounts);
+    await program.rpc.purchasePartial(new anchor.BN(lotSize * 2), new anchor.BN(pricePerLot * 2), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerPurchaseToken.subn(pricePerLot * 2).eq(finalBalances.buyerPurchaseToken));
+    assert.ok(startBalances.buyerSaleToken.addn(lotSize * 2).eq(finalBalances.buyerSaleToken));
+  });
+
+  it("Lets only the seller reprice an escrow", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const assetQty = 10;
+    const newPricePerUnit = 15;
+
+    await doDefaultInit(basicAccounts, 200, assetQty);
+    const updatePriceAccounts = (seller: anchor.web3.PublicKey) => ({
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      seller: seller,
+      mint: basicAccounts.mint.publicKey,
+    });
+
+    await assertProgramError(() => program.rpc.updatePrice(new anchor.BN(1), {
+      accounts: updatePriceAccounts(basicAccounts.buyer.publicKey),
+      signers: [basicAccounts.buyer],
+    }), 'NotSeller');
+
+    await program.rpc.updatePrice(new anchor.BN(newPricePerUnit), {
+      accounts: updatePriceAccounts(basicAccounts.seller.publicKey),
+      signers: [basicAccounts.seller],
+    });
+    const repricedAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(repricedAccount.pricePerLot.eqn(newPricePerUnit));
+    assert.ok(repricedAccount.totalPurchaseCost.eqn(newPricePerUnit * assetQty));
+
+    // purchases pay the new price
+    const startBalances = await getMainBalances(basicAccounts);
+    await doDefaultPurchase(basicAccounts);
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.buyerPurchaseToken.subn(newPricePerUnit * assetQty).eq(finalBalances.buyerPurchaseToken));
+  });
+
+  it("Withdraws part of an escrow and closes it once empty", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const pricePerUnit = 20;
+    const assetQty = 10;
+    const withdrawQty = 4;
+
+    await doDefaultInit(basicAccounts, pricePerUnit * assetQty, assetQty);
+    const startBalances = await getMainBalances(basicAccounts);
+    const withdrawAccounts = {
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      seller: basicAccounts.seller.publicKey,
+      rentPayer: basicAccounts.seller.publicKey,
+      mint: basicAccounts.mint.publicKey,
+      sellFromAccount: basicAccounts.sellFromAccount.address,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    };
+
+    await assertProgramError(() => program.rpc.withdrawPartial(new anchor.BN(assetQty + 1), {
+      accounts: withdrawAccounts,
+      signers: [basicAccounts.seller],
+    }), 'QuantityExceedsEscrow');
+    await program.rpc.withdrawPartial(new anchor.BN(withdrawQty), {
+      accounts: withdrawAccounts,
+      signers: [basicAccounts.seller],
+    });
+
+    const withdrawnAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(withdrawnAccount.totalPurchaseCost.eqn(pricePerUnit * (assetQty - withdrawQty)));
+    assert.ok((await getMainBalances(basicAccounts)).sellerSaleToken.eq(startBalances.sellerSaleToken.addn(withdrawQty)));
+
+    // withdrawing the rest closes the escrow
+    await program.rpc.withdrawPartial(new anchor.BN(assetQty - withdrawQty), {
+      accounts: withdrawAccounts,
+      signers: [basicAccounts.seller],
+    });
+    assert.ok((await getMainBalances(basicAccounts)).sellerSaleToken.eq(startBalances.sellerSaleToken.addn(assetQty)));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Cancels a minted escrow back to a designated account", async () => {
+    // the provider wallet pays for and mints the escrow, so the seller holds none of its tokens
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, provider.wallet.publicKey);
+    const assetQty = 10;
+
+    await program.rpc.tenderFromMint(new anchor.BN(basicAccounts.escrowId), new anchor.BN(20), new anchor.BN(1), new anchor.BN(assetQty), null, { seller: {} }, { exact: {} }, {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        mintAuthority: provider.wallet.publicKey,
+        payer: provider.wallet.publicKey,
+        receiver: basicAccounts.buyer.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    const cancelAccounts = (authority: anchor.web3.PublicKey) => ({
+      escrowAccount: basicAccounts.escrowAccount,
+      escrowTokenAccount: basicAccounts.escrowTokenAccount,
+      authority: authority,
+      rentPayer: provider.wallet.publicKey,
+      mint: basicAccounts.mint.publicKey,
+      destination: basicAccounts.sellFromAccount.address,
+      tokenProgram: splToken.TOKEN_PROGRAM_ID,
+    });
+    await assertProgramError(() => program.rpc.cancelMinted(false, {
+      accounts: cancelAccounts(basicAccounts.seller.publicKey),
+      signers: [basicAccounts.seller],
+    }), 'NotCancelAuthority');
+
+    // nor can the seller withdraw tokens they never held
+    await assertProgramError(() => program.rpc.withdrawPartial(new anchor.BN(1), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        seller: basicAccounts.seller.publicKey,
+        rentPayer: provider.wallet.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        sellFromAccount: basicAccounts.sellFromAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+      signers: [basicAccounts.seller],
+    }), 'MintedEscrow');
+
+    const startBalances = await getMainBalances(basicAccounts);
+    await program.rpc.cancelMinted(false, {
+      accounts: cancelAccounts(provider.wallet.publicKey),
+    });
+    const finalBalances = await getMainBalances(basicAccounts);
+    assert.ok(startBalances.sellerSaleToken.addn(assetQty).eq(finalBalances.sellerSaleToken));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+  });
+
+  it("Rejects cancelling a tendered escrow as minted", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    await doDefaultInit(basicAccounts, 200, 10);
+
+    // the provider wallet is the mint authority, but the seller deposited these tokens
+    await assertProgramError(() => program.rpc.cancelMinted(true, {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        authority: provider.wallet.publicKey,
+        rentPayer: basicAccounts.seller.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        destination: basicAccounts.escrowTokenAccount,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+    }), 'NotMinted');
+  });
+
+  it("Runs independent escrows of the same pair under different ids", async () => {
+    const first: BasicAccounts = await getBasicAccounts(provider);
+    const secondId = 1;
+    const [ secondEscrow, secondBump ] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("escrow"),
+        first.sellerProceedsAccount.address.toBuffer(),
+        first.receiver.toBuffer(),
+        first.mint.publicKey.toBuffer(),
+        first.purchaseMint.publicKey.toBuffer(),
+        first.seller.publicKey.toBuffer(),
+        new anchor.BN(secondId).toArrayLike(Buffer, 'le', 8),
+      ],
+      program.programId,
+    );
+    const second: BasicAccounts = {
+      ...first,
+      escrowAccount: secondEscrow,
+      escrowTokenAccount: await splToken.Token.getAssociatedTokenAddress(splToken.ASSOCIATED_TOKEN_PROGRAM_ID, splToken.TOKEN_PROGRAM_ID, first.mint.publicKey, secondEscrow, true),
+      bumpSeed: secondBump,
+      escrowId: secondId,
+    };
+
+    // the same seller lists the same pair to the same receiver at two prices
+    await doDefaultInit(first, 200, 10);
+    await doDefaultInit(second, 50, 5);
+    const firstAccount = await program.account.escrowAccount.fetch(first.escrowAccount);
+    const secondAccount = await program.account.escrowAccount.fetch(second.escrowAccount);
+    assert.ok(firstAccount.pricePerLot.eqn(20));
+    assert.ok(secondAccount.pricePerLot.eqn(10));
+    assert.ok(secondAccount.escrowId.eqn(secondId));
+
+    // buying the cheaper listing leaves the other untouched
+    await doDefaultPurchase(second);
+    assert.ok(await connection.getAccountInfo(second.escrowAccount) === null);
+    assert.ok((await first.mint.getAccountInfo(first.escrowTokenAccount)).amount.eqn(10));
+  });
+
+  it("Stores the canonical bump whatever the client derives", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    await doDefaultInit(basicAccounts, 200, 10);
+
+    if (hasConfig) {
+      const config = await program.account.configAccount.fetch(configAccount);
+      const [ , configBump ] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("config")], program.programId);
+      assert.ok(config.bumpSeed === configBump);
+    }
+
+    // later tenders keep the bump stored at init
+    await doDefaultInit(basicAccounts, 200, 10);
+    const escrow = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(escrow.bumpSeed === basicAccounts.bumpSeed);
+  });
+
+  it("Tenders from a multisig mint authority once its threshold signs", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, provider.wallet.publicKey);
+    const assetQty = 10;
+
+    // hand the mint to a 2 of 3 multisig
+    const multisigSigners = [anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate()];
+    const multisig = await basicAccounts.mint.createMultisig(2, multisigSigners.map((signer) => signer.publicKey));
+    await basicAccounts.mint.setAuthority(basicAccounts.mint.publicKey, multisig, 'MintTokens', provider.wallet.publicKey, []);
+
+    const tenderFromMultisig = (signers: anchor.web3.Keypair[]) => program.rpc.tenderFromMint(new anchor.BN(basicAccounts.escrowId), new anchor.BN(20), new anchor.BN(1), new anchor.BN(assetQty), null, { seller: {} }, { exact: {} }, {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        mintAuthority: multisig,
+        payer: provider.wallet.publicKey,
+        receiver: basicAccounts.buyer.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+      remainingAccounts: signers.map((signer) => ({ pubkey: signer.publicKey, isSigner: true, isWritable: false })),
+      signers: signers,
+    });
+
+    // a signer passed twice still only counts once, and outsiders don't count at all
+    await assertProgramError(() => tenderFromMultisig([multisigSigners[0], multisigSigners[0]]), 'MultisigThresholdNotMet');
+    await assertProgramError(() => tenderFromMultisig([multisigSigners[0], anchor.web3.Keypair.generate()]), 'MultisigThresholdNotMet');
+
+    await tenderFromMultisig([multisigSigners[0], multisigSigners[2]]);
+    assert.ok((await basicAccounts.mint.getAccountInfo(basicAccounts.escrowTokenAccount)).amount.eqn(assetQty));
+  });
+
+  it("Tenders from a mint whose authority signs directly", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, provider.wallet.publicKey);
+    const assetQty = 10;
+
+    // stands in for a calling program's PDA, which signs for itself with invoke_signed
+    const mintAuthority = anchor.web3.Keypair.generate();
+    await basicAccounts.mint.setAuthority(basicAccounts.mint.publicKey, mintAuthority.publicKey, 'MintTokens', provider.wallet.publicKey, []);
+
+    const tenderSigned = (authority: anchor.web3.Keypair) => program.rpc.tenderFromMintSigned(new anchor.BN(basicAccounts.escrowId), new anchor.BN(20), new anchor.BN(1), new anchor.BN(assetQty), null, { seller: {} }, { exact: {} }, {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        mintAuthority: authority.publicKey,
+        payer: provider.wallet.publicKey,
+        receiver: basicAccounts.buyer.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+      signers: [authority],
+    });
+
+    await assertProgramError(() => tenderSigned(anchor.web3.Keypair.generate()), 'WrongMintAuthority');
+
+    await tenderSigned(mintAuthority);
+    assert.ok((await basicAccounts.mint.getAccountInfo(basicAccounts.escrowTokenAccount)).amount.eqn(assetQty));
+  });
+
+  it("Tenders from a calling program's mint authority PDA through CPI", async () => {
+    const mintCaller = anchor.workspace.MintCaller;
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider, provider.wallet.publicKey);
+    const assetQty = 10;
+
+    // the calling program signs for its PDA with invoke_signed
+    const [ mintAuthority ] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("mint_authority")], mintCaller.programId);
+    await basicAccounts.mint.setAuthority(basicAccounts.mint.publicKey, mintAuthority, 'MintTokens', provider.wallet.publicKey, []);
+
+    await mintCaller.rpc.tender(new anchor.BN(basicAccounts.escrowId), new anchor.BN(20), new anchor.BN(1), new anchor.BN(assetQty), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        mintAuthority: mintAuthority,
+        payer: provider.wallet.publicKey,
+        receiver: basicAccounts.buyer.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        escrowProgram: program.programId,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: splToken.ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    assert.ok((await basicAccounts.mint.getAccountInfo(basicAccounts.escrowTokenAccount)).amount.eqn(assetQty));
+    const escrowAccount = await program.account.escrowAccount.fetch(basicAccounts.escrowAccount);
+    assert.ok(escrowAccount.minted);
+    assert.ok(escrowAccount.totalPurchaseCost.eqn(20 * assetQty));
+  });
+
+  it("Mints purchases from a lazy mint escrow up to its supply cap", async () => {
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    const pricePerLot = 20;
+    const supplyCap = 10;
+    const startSupply = (await basicAccounts.mint.getMintInfo()).supply;
+
+    await doLazyMintInit(basicAccounts, provider.wallet.publicKey, pricePerLot, supplyCap);
+
+    // nothing is minted until it's bought, and the escrow holds the mint authority meanwhile
+    let mintInfo = await basicAccounts.mint.getMintInfo();
+    assert.ok(mintInfo.supply.eq(startSupply));
+    assert.ok(mintInfo.mintAuthority.equals(basicAccounts.escrowAccount));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowTokenAccount) === null);
+
+    await program.rpc.purchasePartial(new anchor.BN(4), new anchor.BN(pricePerLot * 4), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    assert.ok((await basicAccounts.mint.getMintInfo()).supply.eq(startSupply.addn(4)));
+    await assertProgramError(() => program.rpc.purchasePartial(new anchor.BN(supplyCap), new anchor.BN(pricePerLot * supplyCap), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    }), 'QuantityExceedsEscrow');
+
+    // buying out the cap closes the escrow and hands the mint authority back
+    await program.rpc.purchase({
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+    mintInfo = await basicAccounts.mint.getMintInfo();
+    assert.ok(mintInfo.supply.eq(startSupply.addn(supplyCap)));
+    assert.ok(mintInfo.mintAuthority.equals(provider.wallet.publicKey));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+  });
+
+  it("Cancels a lazy mint escrow and hands back the mint authority", async () => {
+    const cancelLazyMint = (basicAccounts: BasicAccounts, authority: anchor.web3.PublicKey, signers: anchor.web3.Keypair[]) => program.rpc.cancelLazyMint({
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        authority: authority,
+        rentPayer: basicAccounts.seller.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+      signers: signers,
+    });
+
+    // the original mint authority cancels, after part of the cap has sold
+    const basicAccounts: BasicAccounts = await getBasicAccounts(provider);
+    await doLazyMintInit(basicAccounts, provider.wallet.publicKey, 20, 10);
+    await program.rpc.purchasePartial(new anchor.BN(4), new anchor.BN(80), {
+      accounts: getPurchaseAccountsBlock(basicAccounts),
+      signers: [basicAccounts.buyer],
+    });
+
+    // the seller's cancel doesn't apply, and outsiders can't cancel
+    await assertProgramError(() => program.rpc.cancel({
+      accounts: await getCancelAccountsBlock(basicAccounts),
+      signers: [basicAccounts.seller],
+    }), 'LazyMintEscrow');
+    await assertProgramError(() => program.rpc.withdrawPartial(new anchor.BN(1), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        seller: basicAccounts.seller.publicKey,
+        rentPayer: basicAccounts.seller.publicKey,
+        mint: basicAccounts.mint.publicKey,
+        sellFromAccount: basicAccounts.sellFromAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+      signers: [basicAccounts.seller],
+    }), 'LazyMintEscrow');
+    await assertProgramError(() => program.rpc.burn(new anchor.BN(1), {
+      accounts: {
+        escrowAccount: basicAccounts.escrowAccount,
+        escrowTokenAccount: basicAccounts.escrowTokenAccount,
+        rentPayer: basicAccounts.seller.publicKey,
+        receiver: basicAccounts.receiver,
+        mint: basicAccounts.mint.publicKey,
+        purchaseMint: basicAccounts.purchaseMint.publicKey,
+        sellerProceedsAccount: basicAccounts.sellerProceedsAccount.address,
+        tokenProgram: splToken.TOKEN_PROGRAM_ID,
+      },
+      signers: [basicAccounts.seller],
+    }), 'LazyMintEscrow');
+    const outsider = anchor.web3.Keypair.generate();
+    await assertProgramError(() => cancelLazyMint(basicAccounts, outsider.publicKey, [outsider]), 'NotCancelAuthority');
+
+    const sellerLamportsBefore = await connection.getBalance(basicAccounts.seller.publicKey);
+    await cancelLazyMint(basicAccounts, provider.wallet.publicKey, []);
+    assert.ok((await basicAccounts.mint.getMintInfo()).mintAuthority.equals(provider.wallet.publicKey));
+    assert.ok(await connection.getAccountInfo(basicAccounts.escrowAccount) === null);
+    assert.ok(await connection.getBalance(basicAccounts.seller.publicKey) > sellerLamportsBefore);
+
+    // the rent payer can cancel too
+    const paidFor: BasicAccounts = await getBasicAccounts(provider);
+    await doLazyMintInit(paidFor, provider.wallet.publicKey, 20, 10);
+    await cancelLazyMint(paidFor, paidFor.seller.publicKey, [paidFor.seller]);
+    assert.ok((await paidFor.mint.getMintInfo()).mintAuthority.equals(provider.wallet.publicKey));
+    assert.ok(await connection.getAccountInfo(paidFor.escrowAccount) === null);
+  });
 });